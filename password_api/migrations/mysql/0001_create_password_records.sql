@@ -0,0 +1,8 @@
+-- The original hand-made table. IF NOT EXISTS lets databases created from the
+-- old README instructions adopt the migration history.
+CREATE TABLE IF NOT EXISTS password_records (
+    account_owner VARCHAR(255),
+    account_name VARCHAR(255),
+    account_username VARCHAR(255),
+    account_password TEXT
+);
@@ -0,0 +1,3 @@
+ALTER TABLE password_records
+    ADD COLUMN account_ciphertext TEXT,
+    ADD COLUMN account_nonce VARCHAR(64);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS vault_metadata (
+    owner VARCHAR(255) NOT NULL PRIMARY KEY,
+    kdf_salt VARCHAR(64) NOT NULL,
+    kdf_memory_kib INT UNSIGNED NOT NULL,
+    kdf_iterations INT UNSIGNED NOT NULL,
+    kdf_parallelism INT UNSIGNED NOT NULL,
+    key_check_ciphertext TEXT NOT NULL,
+    key_check_nonce VARCHAR(64) NOT NULL
+);
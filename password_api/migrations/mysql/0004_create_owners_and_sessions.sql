@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS owners (
+    owner VARCHAR(255) NOT NULL PRIMARY KEY,
+    login_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    token_hash CHAR(64) NOT NULL PRIMARY KEY,
+    owner VARCHAR(255) NOT NULL,
+    expires_at DATETIME NOT NULL,
+    INDEX idx_sessions_owner (owner),
+    FOREIGN KEY (owner) REFERENCES owners (owner) ON DELETE CASCADE
+);
@@ -0,0 +1,38 @@
+-- SQLite databases always start empty, so the whole schema of the MySQL
+-- migrations up to 0005 is created in one step.
+CREATE TABLE password_records (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_owner TEXT,
+    account_name TEXT,
+    account_username TEXT,
+    account_password TEXT,
+    account_ciphertext TEXT,
+    account_nonce TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_password_records_owner ON password_records (account_owner);
+
+CREATE TABLE vault_metadata (
+    owner TEXT NOT NULL PRIMARY KEY,
+    kdf_salt TEXT NOT NULL,
+    kdf_memory_kib INTEGER NOT NULL,
+    kdf_iterations INTEGER NOT NULL,
+    kdf_parallelism INTEGER NOT NULL,
+    key_check_ciphertext TEXT NOT NULL,
+    key_check_nonce TEXT NOT NULL
+);
+
+CREATE TABLE owners (
+    owner TEXT NOT NULL PRIMARY KEY,
+    login_hash TEXT NOT NULL
+);
+
+CREATE TABLE sessions (
+    token_hash TEXT NOT NULL PRIMARY KEY,
+    owner TEXT NOT NULL REFERENCES owners (owner) ON DELETE CASCADE,
+    expires_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_sessions_owner ON sessions (owner);
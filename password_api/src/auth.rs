@@ -0,0 +1,220 @@
+use argon2::{
+    password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rand::{rngs::OsRng, RngCore};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+use crate::HashingPool;
+
+use password_common::{
+    ApiResponse, VaultParams, DEFAULT_KDF_ITERATIONS, DEFAULT_KDF_MEMORY_KIB, DEFAULT_KDF_PARALLELISM,
+};
+
+use crate::AppState;
+
+// How long a token issued by /login stays valid
+pub const SESSION_TTL_SECONDS: u64 = 60 * 60;
+
+// The owner a request's bearer token belongs to. Handlers that take this
+// extractor reject requests without a valid, unexpired session.
+pub struct AuthOwner(pub String);
+
+impl AuthOwner {
+    // Tokens may only touch their own owner's rows
+    pub fn require<T: Serialize>(&self, owner: &str) -> Result<(), (StatusCode, Json<ApiResponse<T>>)> {
+        if self.0 == owner {
+            return Ok(());
+        }
+
+        let status = StatusCode::FORBIDDEN;
+        let response = ApiResponse {
+            message: "Token is not valid for this owner".to_string(),
+            http_code: status.as_u16(),
+            data: None,
+        };
+        Err((status, Json(response)))
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthOwner {
+    type Rejection = (StatusCode, Json<ApiResponse<()>>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let unauthorized = |message: &str| {
+            let status = StatusCode::UNAUTHORIZED;
+            let response = ApiResponse {
+                message: message.to_string(),
+                http_code: status.as_u16(),
+                data: None,
+            };
+            (status, Json(response))
+        };
+
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| unauthorized("Missing bearer token"))?;
+
+        match state.store.session_owner(&hash_token(token)).await {
+            Ok(Some(owner)) => Ok(AuthOwner(owner)),
+            Ok(None) => Err(unauthorized("Invalid or expired token")),
+            Err(e) => {
+                log::error!("DB error: {}", e);
+                let status = StatusCode::INTERNAL_SERVER_ERROR;
+                let response = ApiResponse {
+                    message: "Failed to validate token".to_string(),
+                    http_code: status.as_u16(),
+                    data: None,
+                };
+                Err((status, Json(response)))
+            }
+        }
+    }
+}
+
+// Stands in for owners without an account, so neither /vault nor /login
+// reveals which owners exist
+pub struct Decoys {
+    // Made at startup, so a made-up salt stays the same until a restart
+    key: [u8; 32],
+    login_hash: OnceCell<String>,
+}
+
+impl Decoys {
+    pub fn new() -> Self {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Decoys {
+            key,
+            login_hash: OnceCell::new(),
+        }
+    }
+
+    // Shaped like a real vault created with the default costs
+    pub fn vault_params(&self, owner: &str) -> VaultParams {
+        let digest = Sha256::new()
+            .chain_update(self.key)
+            .chain_update(owner.as_bytes())
+            .finalize();
+        VaultParams {
+            owner: owner.to_string(),
+            kdf_salt: STANDARD.encode(&digest[..16]),
+            kdf_memory_kib: DEFAULT_KDF_MEMORY_KIB,
+            kdf_iterations: DEFAULT_KDF_ITERATIONS,
+            kdf_parallelism: DEFAULT_KDF_PARALLELISM,
+        }
+    }
+
+    // Logins of unknown owners are checked against this, so they take as
+    // long as a wrong password
+    pub async fn login_hash(&self, pool: &HashingPool) -> &str {
+        self.login_hash
+            .get_or_init(|| hash_login_password(pool, "aps-decoy-login"))
+            .await
+    }
+}
+
+// Tokens are random and only their SHA-256 digest is stored, so a leaked
+// sessions table cannot be replayed.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Argon2 runs on the hashing pool so registrations and logins never block
+// the async workers
+pub async fn hash_login_password(pool: &HashingPool, password: &str) -> String {
+    let password = password.to_string();
+    pool.run(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .expect("Failed to hash password")
+            .to_string()
+    })
+    .await
+}
+
+// Checks a candidate against an entry's stored hash. An error means the hash
+// itself is unusable: missing its salt or output, or not a PHC string at all.
+pub async fn verify_entry_password(pool: &HashingPool, hash: &str, password: &str) -> Result<bool, password_hash::Error> {
+    let (hash, password) = (hash.to_string(), password.to_string());
+    pool.run(move || {
+        let parsed = PasswordHash::new(&hash)?;
+        if parsed.salt.is_none() || parsed.hash.is_none() {
+            return Err(password_hash::Error::PhcStringTooShort);
+        }
+        match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+            Ok(()) => Ok(true),
+            Err(password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e),
+        }
+    })
+    .await
+}
+
+// The algorithm, version and costs of a hash without its salt and output
+pub fn hash_parameters(hash: &str) -> Option<String> {
+    let mut parsed = PasswordHash::new(hash).ok()?;
+    parsed.salt = None;
+    parsed.hash = None;
+    Some(parsed.to_string())
+}
+
+pub async fn verify_login_password(pool: &HashingPool, hash: &str, password: &str) -> bool {
+    let (hash, password) = (hash.to_string(), password.to_string());
+    pool.run(move || match PasswordHash::new(&hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::Params;
+
+    const HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHQ$aGFzaGhhc2hoYXNoaGFzaA";
+
+    #[test]
+    fn hash_parameters_drop_salt_and_output() {
+        assert_eq!(hash_parameters(HASH).as_deref(), Some("$argon2id$v=19$m=19456,t=2,p=1"));
+        assert_eq!(hash_parameters("not a hash"), None);
+    }
+
+    // The client decides whether to upgrade a hash by parsing what verify
+    // answers, so the parameters must still read back as Argon2 costs
+    #[test]
+    fn hash_parameters_parse_back_into_costs() {
+        let parameters = hash_parameters(HASH).unwrap();
+        let parsed = PasswordHash::new(&parameters).unwrap();
+        assert_eq!(parsed.algorithm, argon2::Algorithm::Argon2id.ident());
+        assert_eq!(parsed.version, Some(19));
+        let costs = Params::try_from(&parsed).unwrap();
+        assert_eq!((costs.m_cost(), costs.t_cost(), costs.p_cost()), (19456, 2, 1));
+    }
+}
@@ -0,0 +1,489 @@
+use clap::Parser;
+use log::LevelFilter;
+use serde::Deserialize;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::hashing::HashingPool;
+use crate::pepper::Pepper;
+use crate::store::{Backend, PoolConfig};
+
+// Read when --config / APS_CONFIG are not given and the file exists
+pub const DEFAULT_CONFIG_FILE: &str = "password_api.toml";
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:3000";
+const DEFAULT_SQLITE_URL: &str = "sqlite://passwords.db";
+
+// Command-line flags. Every setting can also come from the config file or an
+// environment variable; flags win over the environment, which wins over the file.
+#[derive(Parser, Debug)]
+#[command(name = "password_api", about = "Server for AP's Password Manager", version)]
+pub struct Args {
+    /// TOML config file [env: APS_CONFIG] [default: password_api.toml if present]
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Address and port to listen on [env: APS_LISTEN] [default: 127.0.0.1:3000]
+    #[arg(long)]
+    pub listen: Option<String>,
+    /// error, warn, info, debug, trace or off [env: APS_LOG_LEVEL] [default: info]
+    #[arg(long)]
+    pub log_level: Option<String>,
+    /// mysql, sqlite or memory [env: STORAGE_BACKEND] [default: mysql]
+    #[arg(long)]
+    pub storage_backend: Option<String>,
+    /// Database URL for the mysql and sqlite backends [env: DATABASE_URL]
+    #[arg(long)]
+    pub database_url: Option<String>,
+    /// Largest number of pooled database connections [env: APS_DB_MAX_CONNECTIONS] [default: 5]
+    #[arg(long)]
+    pub max_connections: Option<String>,
+    /// Seconds to wait for a free connection [env: APS_DB_ACQUIRE_TIMEOUT] [default: 30]
+    #[arg(long)]
+    pub acquire_timeout: Option<String>,
+    /// Seconds before an unused connection is closed, 0 to keep them [env: APS_DB_IDLE_TIMEOUT] [default: 600]
+    #[arg(long)]
+    pub idle_timeout: Option<String>,
+    /// PEM certificate chain to serve HTTPS with [env: APS_TLS_CERT]
+    #[arg(long)]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key for --tls-cert [env: APS_TLS_KEY]
+    #[arg(long)]
+    pub tls_key: Option<PathBuf>,
+    /// Largest number of password hashes computed at once [env: APS_MAX_CONCURRENT_HASHES] [default: number of CPUs]
+    #[arg(long)]
+    pub max_concurrent_hashes: Option<String>,
+    /// Key file for sealing stored password hashes [env: APS_PEPPER_KEY_FILE]
+    #[arg(long)]
+    pub pepper_key_file: Option<PathBuf>,
+    /// Let this owner register although it has records from before accounts existed; repeatable [env: APS_ALLOW_CLAIM, comma separated]
+    #[arg(long = "allow-claim", value_name = "OWNER")]
+    pub allow_claim: Vec<String>,
+    /// Apply database migrations and exit
+    #[arg(long)]
+    pub migrate_only: bool,
+    /// Reseal every stored password hash with the current pepper key and exit
+    #[arg(long, conflicts_with = "migrate_only")]
+    pub rotate_pepper: bool,
+}
+
+// Layout of the TOML file; every key is optional
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    listen: Option<String>,
+    log_level: Option<String>,
+    storage: FileStorage,
+    pool: FilePool,
+    tls: FileTls,
+    pepper: FilePepper,
+    hashing: FileHashing,
+    registration: FileRegistration,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileStorage {
+    backend: Option<String>,
+    database_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FilePool {
+    max_connections: Option<u32>,
+    acquire_timeout_secs: Option<u64>,
+    idle_timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileTls {
+    cert_file: Option<PathBuf>,
+    key_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileHashing {
+    max_concurrent: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileRegistration {
+    allow_claim: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FilePepper {
+    key_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+// The validated server settings. Not Debug, since the database URL may hold a password.
+pub struct Config {
+    pub listen: SocketAddr,
+    pub log_level: LevelFilter,
+    pub backend: Backend,
+    pub pool: PoolConfig,
+    pub tls: Option<TlsFiles>,
+    // Seals account password hashes at rest when set
+    pub pepper: Option<Pepper>,
+    // Runs the Argon2 work of logins and registrations
+    pub hashing: HashingPool,
+    // Owners allowed to register and take over records that predate accounts
+    pub allow_claim: Vec<String>,
+    pub migrate_only: bool,
+    pub rotate_pepper: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    // A setting with an unusable value, named as it appears in the config file
+    Invalid { setting: &'static str, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "cannot parse {}: {}", path.display(), e),
+            ConfigError::Invalid { setting, reason } => write!(f, "invalid {}: {}", setting, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(setting: &'static str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        setting,
+        reason: reason.into(),
+    }
+}
+
+// Where environment variables come from: the process environment, or a
+// fixed set in tests
+struct Env<'a> {
+    lookup: &'a dyn Fn(&str) -> Option<String>,
+}
+
+impl Env<'_> {
+    fn get(&self, name: &str) -> Option<String> {
+        (self.lookup)(name).filter(|v| !v.trim().is_empty())
+    }
+
+    // The first of flag, environment variable and file value that is set
+    fn pick<T>(&self, flag: Option<T>, name: &str, file: Option<T>) -> Option<T>
+    where
+        T: From<String>,
+    {
+        flag.or_else(|| self.get(name).map(T::from)).or(file)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(setting: &'static str, value: String) -> Result<T, ConfigError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid(setting, format!("'{}' is not a valid number", value)))
+}
+
+impl Config {
+    // Loads the config file, then applies environment variables and flags on top
+    pub fn load(args: Args) -> Result<Config, ConfigError> {
+        Config::load_with_env(args, &|name| std::env::var(name).ok())
+    }
+
+    fn load_with_env(args: Args, lookup: &dyn Fn(&str) -> Option<String>) -> Result<Config, ConfigError> {
+        let env = Env { lookup };
+        let file = match args.config.clone().or_else(|| env.get("APS_CONFIG").map(PathBuf::from)) {
+            Some(path) => read_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => read_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            None => FileConfig::default(),
+        };
+
+        let listen = env.pick(args.listen, "APS_LISTEN", file.listen).unwrap_or_else(|| DEFAULT_LISTEN.to_string());
+        let listen = listen
+            .trim()
+            .parse::<SocketAddr>()
+            .map_err(|_| invalid("listen", format!("'{}' is not an address like 127.0.0.1:3000", listen)))?;
+
+        let log_level = env.pick(args.log_level, "APS_LOG_LEVEL", file.log_level).unwrap_or_else(|| "info".to_string());
+        let log_level = log_level.trim().parse::<LevelFilter>().map_err(|_| {
+            invalid(
+                "log_level",
+                format!("'{}' is not one of error, warn, info, debug, trace or off", log_level),
+            )
+        })?;
+
+        let backend = backend(
+            env.pick(args.storage_backend, "STORAGE_BACKEND", file.storage.backend),
+            env.pick(args.database_url, "DATABASE_URL", file.storage.database_url),
+        )?;
+
+        let pool = pool(&env, &args.max_connections, &args.acquire_timeout, &args.idle_timeout, &file.pool)?;
+
+        let tls = tls(
+            env.pick(args.tls_cert, "APS_TLS_CERT", file.tls.cert_file),
+            env.pick(args.tls_key, "APS_TLS_KEY", file.tls.key_file),
+        )?;
+
+        let hashing = match env.pick(args.max_concurrent_hashes, "APS_MAX_CONCURRENT_HASHES", None) {
+            Some(v) => Some(parse_number("hashing.max_concurrent", v)?),
+            None => file.hashing.max_concurrent,
+        };
+        if hashing == Some(0) {
+            return Err(invalid("hashing.max_concurrent", "must be at least 1"));
+        }
+        let hashing = hashing.map_or_else(HashingPool::default, HashingPool::new);
+
+        let pepper = match env.pick(args.pepper_key_file, "APS_PEPPER_KEY_FILE", file.pepper.key_file) {
+            Some(path) => Some(Pepper::load(&path).map_err(|e| invalid("pepper.key_file", e))?),
+            None => None,
+        };
+        if args.rotate_pepper && pepper.is_none() {
+            return Err(invalid("pepper.key_file", "must be set to rotate the pepper"));
+        }
+
+        let allow_claim = if !args.allow_claim.is_empty() {
+            args.allow_claim
+        } else if let Some(list) = env.get("APS_ALLOW_CLAIM") {
+            list.split(',').map(|owner| owner.trim().to_string()).collect()
+        } else {
+            file.registration.allow_claim.unwrap_or_default()
+        };
+        if allow_claim.iter().any(|owner| owner.is_empty()) {
+            return Err(invalid("registration.allow_claim", "owner names cannot be empty"));
+        }
+
+        Ok(Config {
+            listen,
+            log_level,
+            backend,
+            pool,
+            tls,
+            pepper,
+            hashing,
+            allow_claim,
+            migrate_only: args.migrate_only,
+            rotate_pepper: args.rotate_pepper,
+        })
+    }
+}
+
+fn read_file(path: &Path) -> Result<FileConfig, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+    toml::from_str(&text).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+}
+
+fn backend(kind: Option<String>, database_url: Option<String>) -> Result<Backend, ConfigError> {
+    let kind = kind.unwrap_or_else(|| "mysql".to_string());
+
+    match kind.trim().to_lowercase().as_str() {
+        "mysql" => database_url
+            .map(Backend::MySql)
+            .ok_or_else(|| invalid("storage.database_url", "must be set for the mysql backend")),
+        "sqlite" => Ok(Backend::Sqlite(
+            database_url.unwrap_or_else(|| DEFAULT_SQLITE_URL.to_string()),
+        )),
+        "memory" => Ok(Backend::Memory),
+        other => Err(invalid(
+            "storage.backend",
+            format!("'{}' is not one of mysql, sqlite or memory", other),
+        )),
+    }
+}
+
+fn pool(
+    env: &Env,
+    max_connections: &Option<String>,
+    acquire_timeout: &Option<String>,
+    idle_timeout: &Option<String>,
+    file: &FilePool,
+) -> Result<PoolConfig, ConfigError> {
+    let defaults = PoolConfig::default();
+
+    let max_connections = match env.pick(max_connections.clone(), "APS_DB_MAX_CONNECTIONS", None) {
+        Some(v) => parse_number("pool.max_connections", v)?,
+        None => file.max_connections.unwrap_or(defaults.max_connections),
+    };
+    if max_connections == 0 {
+        return Err(invalid("pool.max_connections", "must be at least 1"));
+    }
+
+    let acquire_secs = match env.pick(acquire_timeout.clone(), "APS_DB_ACQUIRE_TIMEOUT", None) {
+        Some(v) => parse_number("pool.acquire_timeout_secs", v)?,
+        None => file
+            .acquire_timeout_secs
+            .unwrap_or(defaults.acquire_timeout.as_secs()),
+    };
+    if acquire_secs == 0 {
+        return Err(invalid("pool.acquire_timeout_secs", "must be at least 1 second"));
+    }
+
+    let default_idle = defaults.idle_timeout.map(|d| d.as_secs()).unwrap_or(0);
+    let idle_secs: u64 = match env.pick(idle_timeout.clone(), "APS_DB_IDLE_TIMEOUT", None) {
+        Some(v) => parse_number("pool.idle_timeout_secs", v)?,
+        None => file.idle_timeout_secs.unwrap_or(default_idle),
+    };
+
+    Ok(PoolConfig {
+        max_connections,
+        acquire_timeout: Duration::from_secs(acquire_secs),
+        idle_timeout: (idle_secs > 0).then(|| Duration::from_secs(idle_secs)),
+    })
+}
+
+// Both files or neither, and both must be readable
+fn tls(cert: Option<PathBuf>, key: Option<PathBuf>) -> Result<Option<TlsFiles>, ConfigError> {
+    let (cert, key) = match (cert, key) {
+        (None, None) => return Ok(None),
+        (Some(cert), Some(key)) => (cert, key),
+        (Some(_), None) => return Err(invalid("tls.key_file", "must be set together with tls.cert_file")),
+        (None, Some(_)) => return Err(invalid("tls.cert_file", "must be set together with tls.key_file")),
+    };
+
+    for (setting, path) in [("tls.cert_file", &cert), ("tls.key_file", &key)] {
+        if let Err(e) = std::fs::File::open(path) {
+            return Err(invalid(setting, format!("cannot open {}: {}", path.display(), e)));
+        }
+    }
+
+    Ok(Some(TlsFiles { cert, key }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    // Loads with these flags and environment variables only. Unless they say
+    // otherwise, an empty config file stands in for any password_api.toml in
+    // the working directory, and the memory backend for mysql, which needs a URL.
+    fn load(flags: &[&str], env: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let empty = file("");
+        let mut vars = HashMap::from([
+            ("APS_CONFIG".to_string(), empty.path().to_str().unwrap().to_string()),
+            ("STORAGE_BACKEND".to_string(), "memory".to_string()),
+        ]);
+        vars.extend(env.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+
+        let args = Args::parse_from(std::iter::once(&"password_api").chain(flags));
+        Config::load_with_env(args, &|name| vars.get(name).cloned())
+    }
+
+    fn file(text: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+        file
+    }
+
+    fn invalid_setting(result: Result<Config, ConfigError>) -> &'static str {
+        match result.err().expect("the config is rejected") {
+            ConfigError::Invalid { setting, .. } => setting,
+            other => panic!("expected an invalid setting, got {}", other),
+        }
+    }
+
+    #[test]
+    fn flags_win_over_env_which_wins_over_file() {
+        let config = file("listen = \"127.0.0.1:4001\"\n[pool]\nmax_connections = 7\nidle_timeout_secs = 0\n");
+        let path = config.path().to_str().unwrap();
+
+        let loaded = load(&["--config", path], &[]).unwrap();
+        assert_eq!(loaded.listen.port(), 4001);
+        assert_eq!(loaded.pool.max_connections, 7);
+        assert_eq!(loaded.pool.idle_timeout, None);
+
+        let env = [("APS_LISTEN", "127.0.0.1:4002"), ("APS_DB_MAX_CONNECTIONS", "8")];
+        let loaded = load(&["--config", path], &env).unwrap();
+        assert_eq!(loaded.listen.port(), 4002);
+        assert_eq!(loaded.pool.max_connections, 8);
+
+        let flags = ["--config", path, "--listen", "127.0.0.1:4003", "--max-connections", "9"];
+        let loaded = load(&flags, &env).unwrap();
+        assert_eq!(loaded.listen.port(), 4003);
+        assert_eq!(loaded.pool.max_connections, 9);
+
+        // The config file itself can come from the environment, and blank
+        // variables count as unset
+        let loaded = load(&[], &[("APS_CONFIG", path), ("APS_LISTEN", " ")]).unwrap();
+        assert_eq!(loaded.listen.port(), 4001);
+    }
+
+    #[test]
+    fn defaults_apply_when_nothing_is_set() {
+        let loaded = load(&[], &[]).unwrap();
+        assert_eq!(loaded.listen, DEFAULT_LISTEN.parse().unwrap());
+        assert_eq!(loaded.log_level, LevelFilter::Info);
+        assert!(matches!(loaded.backend, Backend::Memory));
+        assert_eq!(loaded.pool.max_connections, PoolConfig::default().max_connections);
+        assert!(loaded.tls.is_none() && loaded.pepper.is_none() && loaded.allow_claim.is_empty());
+    }
+
+    #[test]
+    fn unusable_values_name_their_setting() {
+        assert_eq!(invalid_setting(load(&["--listen", "localhost"], &[])), "listen");
+        assert_eq!(invalid_setting(load(&[], &[("APS_LISTEN", "127.0.0.1")])), "listen");
+        assert_eq!(invalid_setting(load(&["--max-connections", "0"], &[])), "pool.max_connections");
+        assert_eq!(invalid_setting(load(&["--max-connections", "many"], &[])), "pool.max_connections");
+        assert_eq!(invalid_setting(load(&["--log-level", "loud"], &[])), "log_level");
+        assert_eq!(
+            invalid_setting(load(&["--storage-backend", "mysql"], &[])),
+            "storage.database_url"
+        );
+
+        let config = file("[pool]\nmax_connections = 0\n");
+        let flags = ["--config", config.path().to_str().unwrap()];
+        assert_eq!(invalid_setting(load(&flags, &[])), "pool.max_connections");
+
+        assert!(matches!(
+            load(&["--config", "/nonexistent/password_api.toml"], &[]),
+            Err(ConfigError::Read(..))
+        ));
+        let unknown = file("listne = \"127.0.0.1:3000\"\n");
+        assert!(matches!(
+            load(&["--config", unknown.path().to_str().unwrap()], &[]),
+            Err(ConfigError::Parse(..))
+        ));
+    }
+
+    #[test]
+    fn tls_needs_both_files() {
+        let cert = file("cert");
+        let key = file("key");
+        let (cert, key) = (cert.path().to_str().unwrap(), key.path().to_str().unwrap());
+
+        assert_eq!(invalid_setting(load(&["--tls-cert", cert], &[])), "tls.key_file");
+        assert_eq!(invalid_setting(load(&[], &[("APS_TLS_KEY", key)])), "tls.cert_file");
+        assert_eq!(
+            invalid_setting(load(&["--tls-cert", cert, "--tls-key", "/nonexistent/key.pem"], &[])),
+            "tls.key_file"
+        );
+
+        let loaded = load(&["--tls-cert", cert], &[("APS_TLS_KEY", key)]).unwrap();
+        assert_eq!(loaded.tls.unwrap().key.to_str(), Some(key));
+    }
+
+    #[test]
+    fn rotating_the_pepper_needs_a_key_file() {
+        assert_eq!(invalid_setting(load(&["--rotate-pepper"], &[])), "pepper.key_file");
+
+        let keys = file("current = \"k1\"\n[keys]\n\"k1\" = \"AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=\"\n");
+        let keys = keys.path().to_str().unwrap();
+        let loaded = load(&["--rotate-pepper"], &[("APS_PEPPER_KEY_FILE", keys)]).unwrap();
+        assert!(loaded.rotate_pepper && loaded.pepper.is_some());
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+// Runs the server's password hashing on tokio's blocking threads so it never
+// stalls the async workers, with at most `limit` hashes in flight. Every Argon2 call
+// allocates its whole memory cost, so a burst of requests waits for a permit
+// instead of running all at once.
+#[derive(Clone)]
+pub struct HashingPool {
+    permits: Arc<Semaphore>,
+    limit: usize,
+}
+
+impl HashingPool {
+    // A limit of 0 is raised to 1
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        HashingPool {
+            permits: Arc::new(Semaphore::new(limit)),
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    // Runs the closure once a permit is free. The permit travels with the
+    // blocking task, so a caller that gives up waiting does not free it early.
+    pub async fn run<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the hashing pool is never closed");
+        let task = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            f()
+        });
+        match task.await {
+            Ok(value) => value,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+// One hash per available core
+impl Default for HashingPool {
+    fn default() -> Self {
+        HashingPool::new(std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
@@ -0,0 +1,541 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use password_common::{
+    generate_password, routes, AddEntry, ApiResponse, Credentials, Entry, GeneratedPassword, KeyCheck,
+    LoginResponse, PasswordPolicy, RegisterRequest, UpdateEntry, VaultParams, VerifyRequest, VerifyResult,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use axum::http::StatusCode;
+
+pub mod config;
+mod auth;
+pub mod hashing;
+pub use hashing::HashingPool;
+pub mod pepper;
+use auth::{AuthOwner, Decoys};
+pub mod store;
+use store::{RecordStore, StoreError};
+
+#[derive(Clone)]
+struct AppState {
+    store: Arc<dyn RecordStore>,
+    // Where every Argon2 hash and verification runs
+    hashing: HashingPool,
+    decoys: Arc<Decoys>,
+    // Owners whose records predate accounts and who may register anyway
+    allow_claim: Arc<HashSet<String>>,
+}
+
+// The full password_api router over the given store. Used by the server
+// binary and by clients that embed the API in-process.
+pub fn app(store: Arc<dyn RecordStore>, hashing: HashingPool, allow_claim: Vec<String>) -> Router {
+    let app_state = AppState {
+        store,
+        hashing,
+        decoys: Arc::new(Decoys::new()),
+        allow_claim: Arc::new(allow_claim.into_iter().collect()),
+    };
+
+    Router::new()
+        .route(routes::ADD, post(add_entry))
+        .route(routes::ENTRIES, get(get_entries))
+        .route(
+            routes::ENTRY_BY_ID,
+            get(get_entry).put(update_entry).delete(delete_entry_by_id),
+        )
+        .route(routes::VERIFY_ENTRY, post(verify_entry))
+        .route(routes::DELETE_BY_NAME, delete(delete_entry))
+        .route(routes::REGISTER, post(register))
+        .route(routes::LOGIN, post(login))
+        .route(routes::VAULT, get(get_vault))
+        .route(routes::KEY_CHECK, get(get_key_check))
+        .route(routes::GENERATE, post(generate))
+        .with_state(app_state)
+}
+
+// Answers with the stored record, without its password hash, so the caller
+// learns the new id and timestamps
+async fn add_entry(
+    auth: AuthOwner,
+    State(state): State<AppState>,
+    Json(payload): Json<AddEntry>,
+) -> (StatusCode, Json<ApiResponse<Entry>>) {
+    if let Err(rejection) = auth.require(&payload.owner) {
+        return rejection;
+    }
+
+    let result = match state.store.add_entry(&payload).await {
+        Ok(id) => state.store.get_entry_without_hash(&payload.owner, id).await,
+        Err(e) => Err(e),
+    };
+
+    let (status, message, data) = match result {
+        Ok(Some(entry)) => (StatusCode::CREATED, "Record added successfully", Some(entry)),
+        Ok(None) => {
+            log::error!("Record added for {} could not be read back", payload.owner);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to add record", None)
+        }
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to add record", None)
+        }
+    };
+
+    let response = ApiResponse {
+        message: message.to_string(),
+        http_code: status.as_u16(),
+        data,
+    };
+
+    (status, Json(response))
+}
+
+// Password hashes stay on the server unless a caller explicitly asks for them
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct EntryQuery {
+    include_hash: bool,
+}
+
+async fn get_entries(
+    auth: AuthOwner,
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(query): Query<EntryQuery>,
+) -> (StatusCode, Json<ApiResponse<Vec<Entry>>>) {
+    if let Err(rejection) = auth.require(&owner) {
+        return rejection;
+    }
+
+    let rows_result = if query.include_hash {
+        state.store.list_entries(&owner).await
+    } else {
+        state.store.list_entries_without_hashes(&owner).await
+    };
+
+    match rows_result {
+        Ok(rows) => {
+            let status = StatusCode::OK;
+            let response = ApiResponse {
+                message: "Entries retrieved successfully".to_string(),
+                http_code: status.as_u16(),
+                data: Some(rows),
+            };
+            (status, Json(response))
+        }
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            let status = StatusCode::INTERNAL_SERVER_ERROR;
+            let response = ApiResponse {
+                message: "Failed to retrieve entries".to_string(),
+                http_code: status.as_u16(),
+                data: None,
+            };
+            (status, Json(response))
+        }
+    }
+}
+
+async fn get_entry(
+    auth: AuthOwner,
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Query(query): Query<EntryQuery>,
+) -> (StatusCode, Json<ApiResponse<Entry>>) {
+    // Records of other owners are reported as missing rather than forbidden
+    let row_result = if query.include_hash {
+        state.store.get_entry(&auth.0, id).await
+    } else {
+        state.store.get_entry_without_hash(&auth.0, id).await
+    };
+
+    let (status, message, data) = match row_result {
+        Ok(Some(entry)) => (StatusCode::OK, "Entry retrieved successfully", Some(entry)),
+        Ok(None) => (StatusCode::NOT_FOUND, "No matching records found.", None),
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to retrieve entry", None)
+        }
+    };
+
+    let response = ApiResponse {
+        message: message.to_string(),
+        http_code: status.as_u16(),
+        data,
+    };
+
+    (status, Json(response))
+}
+
+// Checks a candidate password against the stored hash, so the hash itself
+// never has to leave the server
+async fn verify_entry(
+    auth: AuthOwner,
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Json(payload): Json<VerifyRequest>,
+) -> (StatusCode, Json<ApiResponse<VerifyResult>>) {
+    let respond = |status: StatusCode, message: &str, data: Option<VerifyResult>| {
+        let response = ApiResponse {
+            message: message.to_string(),
+            http_code: status.as_u16(),
+            data,
+        };
+        (status, Json(response))
+    };
+
+    let hash = match state.store.get_entry(&auth.0, id).await {
+        Ok(Some(entry)) => entry.account_password.filter(|h| !h.is_empty()),
+        Ok(None) => return respond(StatusCode::NOT_FOUND, "No matching records found.", None),
+        // The server's keys are at fault, not the record
+        Err(e @ StoreError::Pepper { .. }) => {
+            log::error!("{}", e);
+            return respond(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Stored password hash cannot be unsealed with the server's pepper keys",
+                None,
+            );
+        }
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            return respond(StatusCode::INTERNAL_SERVER_ERROR, "Failed to retrieve entry", None);
+        }
+    };
+    let Some(hash) = hash else {
+        return respond(StatusCode::UNPROCESSABLE_ENTITY, "Entry has no stored password hash", None);
+    };
+
+    match auth::verify_entry_password(&state.hashing, &hash, &payload.password).await {
+        Ok(true) => {
+            let result = VerifyResult {
+                matches: true,
+                hash_parameters: auth::hash_parameters(&hash),
+            };
+            respond(StatusCode::OK, "Password matches", Some(result))
+        }
+        Ok(false) => {
+            let result = VerifyResult {
+                matches: false,
+                hash_parameters: None,
+            };
+            respond(StatusCode::OK, "Password does not match", Some(result))
+        }
+        Err(e) => {
+            log::warn!("Record {} has an unusable password hash: {}", id, e);
+            respond(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                &format!("Stored password hash is damaged ({})", e),
+                None,
+            )
+        }
+    }
+}
+
+async fn update_entry(
+    auth: AuthOwner,
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Json(payload): Json<UpdateEntry>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let password_fields = [&payload.password, &payload.ciphertext, &payload.nonce];
+    let changing_password = password_fields.iter().any(|f| f.is_some());
+    if changing_password && !password_fields.iter().all(|f| f.is_some()) {
+        let status = StatusCode::BAD_REQUEST;
+        let response = ApiResponse {
+            message: "Password, ciphertext and nonce must be updated together".to_string(),
+            http_code: status.as_u16(),
+            data: None,
+        };
+        return (status, Json(response));
+    }
+
+    let result = state.store.update_entry(&auth.0, id, &payload).await;
+
+    let (status, message) = match result {
+        Ok(true) => (StatusCode::OK, "Record updated successfully"),
+        Ok(false) => (StatusCode::NOT_FOUND, "No matching records found."),
+        Err(e) => {
+            log::error!("Update error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update record")
+        }
+    };
+
+    let response = ApiResponse {
+        message: message.to_string(),
+        http_code: status.as_u16(),
+        data: None,
+    };
+
+    (status, Json(response))
+}
+
+async fn delete_entry(
+    auth: AuthOwner,
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    if let Err(rejection) = auth.require(&owner) {
+        return rejection;
+    }
+
+    let result = state.store.delete_entries_by_name(&owner, &name).await;
+
+    match result {
+        Ok(deleted) if deleted > 0 => {
+            let status = StatusCode::OK;
+            let response = ApiResponse {
+                message: "Record deleted successfully".to_string(),
+                http_code: status.as_u16(),
+                data: None,
+            };
+            (status, Json(response))
+        }
+        Ok(_) => {
+            let status = StatusCode::NOT_FOUND;
+            let response = ApiResponse {
+                message: "No matching records found.".to_string(),
+                http_code: status.as_u16(),
+                data: None,
+            };
+            (status, Json(response))
+        }
+        Err(e) => {
+            log::error!("Delete error: {:?}", e);
+            let status = StatusCode::INTERNAL_SERVER_ERROR;
+            let response = ApiResponse {
+                message: "Failed to delete record".to_string(),
+                http_code: status.as_u16(),
+                data: None,
+            };
+            (status, Json(response))
+        }
+    }
+}
+
+async fn delete_entry_by_id(
+    auth: AuthOwner,
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let result = state.store.delete_entry(&auth.0, id).await;
+
+    let (status, message) = match result {
+        Ok(true) => (StatusCode::OK, "Record deleted successfully"),
+        Ok(false) => (StatusCode::NOT_FOUND, "No matching records found."),
+        Err(e) => {
+            log::error!("Delete error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete record")
+        }
+    };
+
+    let response = ApiResponse {
+        message: message.to_string(),
+        http_code: status.as_u16(),
+        data: None,
+    };
+
+    (status, Json(response))
+}
+
+async fn register(
+    State(state): State<AppState>,
+    Json(payload): Json<RegisterRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let respond = |status: StatusCode, message: &str| {
+        let response = ApiResponse {
+            message: message.to_string(),
+            http_code: status.as_u16(),
+            data: None,
+        };
+        (status, Json(response))
+    };
+
+    if payload.owner.is_empty() || payload.password.is_empty() {
+        return respond(StatusCode::BAD_REQUEST, "Owner and password are required");
+    }
+    if payload.vault.owner != payload.owner {
+        return respond(StatusCode::BAD_REQUEST, "Vault owner does not match account owner");
+    }
+
+    // Records stored before owner accounts existed belong to no account, and
+    // whoever registered the name first would get them. Taking them over needs
+    // the operator's go-ahead.
+    let existing = match state.store.get_login_hash(&payload.owner).await {
+        Ok(Some(_)) => return respond(StatusCode::CONFLICT, "This owner is already registered"),
+        Ok(None) => state.store.list_entries_without_hashes(&payload.owner).await,
+        Err(e) => Err(e),
+    };
+    let unclaimed = match existing {
+        Ok(records) => records.len(),
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            return respond(StatusCode::INTERNAL_SERVER_ERROR, "Failed to register owner");
+        }
+    };
+    if unclaimed > 0 && !state.allow_claim.contains(&payload.owner) {
+        log::warn!(
+            "Refused to register {}, who has {} records from before accounts existed",
+            payload.owner,
+            unclaimed
+        );
+        return respond(
+            StatusCode::CONFLICT,
+            "This owner has records from before accounts existed; ask the server operator to allow claiming them",
+        );
+    }
+
+    let login_hash = auth::hash_login_password(&state.hashing, &payload.password).await;
+
+    match state.store.register_owner(&login_hash, &payload.vault).await {
+        Ok(()) => {
+            if unclaimed > 0 {
+                log::info!("{} registered and claimed {} older records", payload.owner, unclaimed);
+            }
+            respond(StatusCode::CREATED, "Owner registered successfully")
+        }
+        Err(StoreError::Conflict) => respond(StatusCode::CONFLICT, "This owner is already registered"),
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            respond(StatusCode::INTERNAL_SERVER_ERROR, "Failed to register owner")
+        }
+    }
+}
+
+async fn login(
+    State(state): State<AppState>,
+    Json(payload): Json<Credentials>,
+) -> (StatusCode, Json<ApiResponse<LoginResponse>>) {
+    let respond = |status: StatusCode, message: &str, data: Option<LoginResponse>| {
+        let response = ApiResponse {
+            message: message.to_string(),
+            http_code: status.as_u16(),
+            data,
+        };
+        (status, Json(response))
+    };
+
+    let login_hash = match state.store.get_login_hash(&payload.owner).await {
+        Ok(Some(hash)) => hash,
+        Ok(None) => {
+            let decoy = state.decoys.login_hash(&state.hashing).await;
+            auth::verify_login_password(&state.hashing, decoy, &payload.password).await;
+            return respond(StatusCode::UNAUTHORIZED, "Invalid owner or password", None);
+        }
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            return respond(StatusCode::INTERNAL_SERVER_ERROR, "Failed to log in", None);
+        }
+    };
+
+    if !auth::verify_login_password(&state.hashing, &login_hash, &payload.password).await {
+        return respond(StatusCode::UNAUTHORIZED, "Invalid owner or password", None);
+    }
+
+    let token = auth::generate_token();
+    let result = state
+        .store
+        .create_session(&auth::hash_token(&token), &payload.owner, auth::SESSION_TTL_SECONDS)
+        .await;
+
+    match result {
+        Ok(()) => {
+            let data = LoginResponse {
+                token,
+                expires_in: auth::SESSION_TTL_SECONDS,
+            };
+            respond(StatusCode::OK, "Logged in successfully", Some(data))
+        }
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            respond(StatusCode::INTERNAL_SERVER_ERROR, "Failed to log in", None)
+        }
+    }
+}
+
+// Needs no session, so it only hands out what deriving the key takes.
+// Unknown owners get the same kind of answer as registered ones.
+async fn get_vault(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+) -> (StatusCode, Json<ApiResponse<VaultParams>>) {
+    let row_result = state.store.get_vault(&owner).await;
+
+    let (status, message, data) = match row_result {
+        Ok(Some(header)) => (StatusCode::OK, "Vault retrieved successfully", Some(VaultParams::from(&header))),
+        Ok(None) => (StatusCode::OK, "Vault retrieved successfully", Some(state.decoys.vault_params(&owner))),
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to retrieve vault", None)
+        }
+    };
+
+    let response = ApiResponse {
+        message: message.to_string(),
+        http_code: status.as_u16(),
+        data,
+    };
+
+    (status, Json(response))
+}
+
+// The key check would let anyone test master passwords offline, so only
+// the owner's own session gets it
+async fn get_key_check(
+    auth: AuthOwner,
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+) -> (StatusCode, Json<ApiResponse<KeyCheck>>) {
+    if let Err(rejection) = auth.require(&owner) {
+        return rejection;
+    }
+
+    let row_result = state.store.get_vault(&owner).await;
+
+    let (status, message, data) = match row_result {
+        Ok(Some(header)) => {
+            let check = KeyCheck {
+                ciphertext: header.key_check_ciphertext,
+                nonce: header.key_check_nonce,
+            };
+            (StatusCode::OK, "Key check retrieved successfully", Some(check))
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, "No vault exists for this owner", None),
+        Err(e) => {
+            log::error!("DB error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to retrieve key check", None)
+        }
+    };
+
+    let response = ApiResponse {
+        message: message.to_string(),
+        http_code: status.as_u16(),
+        data,
+    };
+
+    (status, Json(response))
+}
+
+// Needs no session; nothing is stored and the password only goes back to the caller
+async fn generate(Json(policy): Json<PasswordPolicy>) -> (StatusCode, Json<ApiResponse<GeneratedPassword>>) {
+    let (status, message, data) = match generate_password(&policy) {
+        Ok(password) => (
+            StatusCode::OK,
+            "Password generated".to_string(),
+            Some(GeneratedPassword { password }),
+        ),
+        Err(e) => (StatusCode::BAD_REQUEST, format!("Invalid password policy: {}", e), None),
+    };
+
+    let response = ApiResponse {
+        message,
+        http_code: status.as_u16(),
+        data,
+    };
+
+    (status, Json(response))
+}
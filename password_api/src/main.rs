@@ -1,191 +1,191 @@
-use axum::{
-    extract::{Path, State},
-    routing::{delete, get, post},
-    Json, Router,
-};
-use serde::{Deserialize, Serialize};
-use sqlx::mysql::MySqlPoolOptions;
-use std::net::SocketAddr;
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use clap::Parser;
 use dotenvy::dotenv;
 use hyper::Server;
-use axum::http::StatusCode;
-
-#[derive(Serialize)]
-pub struct ApiResponse<T: Serialize> {
-    message: String,
-    http_code: u16,
-    data: Option<T>,
-}
+use password_api::config::{Args, Config, TlsFiles};
+use password_api::store::{self, PepperedStore, RecordStore};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::process::ExitCode;
 
-#[derive(Clone)]
-struct AppState {
-    db: sqlx::MySqlPool,
-}
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
 
-#[derive(Debug, Deserialize, Serialize)]
-struct AddEntry {
-    owner: String,
-    name: String,
-    username: String,
-    password: String,
-}
+    let mut config = match Config::load(Args::parse()) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Entry {
-    account_owner: Option<String>,
-    account_name: Option<String>,
-    account_username: Option<String>,
-    account_password: Option<String>,
-}
+    env_logger::Builder::new()
+        .filter_level(config.log_level)
+        .init();
 
-#[tokio::main]
-async fn main() {
-    dotenv().ok();
+    let store = match store::open(&config.backend, &config.pool).await {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to connect to DB: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    // Bring the schema up to date before serving; --migrate-only stops here
+    if let Err(e) = store.migrate().await {
+        eprintln!("Failed to run database migrations: {}", e);
+        return ExitCode::FAILURE;
+    }
+    if config.migrate_only {
+        println!("Database migrations applied.");
+        return ExitCode::SUCCESS;
+    }
 
-    let db_pool = MySqlPoolOptions::new()
-        .max_connections(5)
-        .connect(&db_url)
-        .await
-        .expect("Failed to connect to DB");
+    let store: Arc<dyn RecordStore> = match config.pepper.take() {
+        Some(pepper) => {
+            log::info!("Sealing password hashes with pepper key '{}'", pepper.current_key_id());
+            let peppered = PepperedStore::new(store, pepper);
+            if config.rotate_pepper {
+                return rotate_pepper(&peppered).await;
+            }
+            Arc::new(peppered)
+        }
+        None => store,
+    };
 
-    let app_state = AppState { db: db_pool };
+    log::info!("Computing at most {} password hashes at once", config.hashing.limit());
+    if !config.allow_claim.is_empty() {
+        log::info!("Owners allowed to claim older records: {}", config.allow_claim.join(", "));
+    }
+    let app = password_api::app(store, config.hashing.clone(), config.allow_claim.clone());
 
-    let app = Router::new()
-        .route("/add", post(add_entry))
-        .route("/entries/:owner", get(get_entries))
-        .route("/delete/:owner/:name", delete(delete_entry))
-        .with_state(app_state);
+    match &config.tls {
+        Some(tls) => serve_https(app, config.listen, tls).await,
+        None => serve_http(app, config.listen).await,
+    }
+}
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    println!("Server running at http://{}", addr);
-    Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+async fn rotate_pepper(store: &PepperedStore) -> ExitCode {
+    match store.rotate().await {
+        Ok(rotation) if rotation.failed == 0 => {
+            println!("Resealed {} password hashes.", rotation.resealed);
+            ExitCode::SUCCESS
+        }
+        Ok(rotation) => {
+            eprintln!(
+                "Resealed {} password hashes; {} could not be opened and still use an old key or are damaged.",
+                rotation.resealed, rotation.failed
+            );
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Failed to rotate the pepper: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
 
-async fn add_entry(
-    State(state): State<AppState>,
-    Json(payload): Json<AddEntry>,
-) -> (StatusCode, Json<ApiResponse<AddEntry>>) {
-    let result = sqlx::query!(
-        r#"
-        INSERT INTO password_records (account_owner, account_name, account_username, account_password)
-        VALUES (?, ?, ?, ?)
-        "#,
-        payload.owner,
-        payload.name,
-        payload.username,
-        payload.password,
-    )
-    .execute(&state.db)
-    .await;
-
-    let (status, message) = match result {
-        Ok(_) => (StatusCode::CREATED, "Record added successfully".to_string()),
+async fn serve_http(app: Router, listen: SocketAddr) -> ExitCode {
+    let server = match Server::try_bind(&listen) {
+        Ok(builder) => builder.serve(app.into_make_service()),
         Err(e) => {
-            eprintln!("DB error: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to add record".to_string())
+            eprintln!("Failed to listen on {}: {}", listen, e);
+            return ExitCode::FAILURE;
         }
     };
 
-    let response = ApiResponse {
-        message,
-        http_code: status.as_u16(),
-        data: match status {
-            StatusCode::CREATED => Some(payload),
-            _ => None,
-        },
-    };
+    log::info!("Server running at http://{}", listen);
+    if let Err(e) = server.await {
+        eprintln!("Server stopped: {}", e);
+        return ExitCode::FAILURE;
+    }
 
-    (status, Json(response))
+    ExitCode::SUCCESS
 }
 
-async fn get_entries(
-    State(state): State<AppState>,
-    Path(owner): Path<String>,
-) -> (StatusCode, Json<ApiResponse<Vec<Entry>>>) {
-    let rows_result = sqlx::query_as!(
-        Entry,
-        r#"
-        SELECT account_owner, account_name, account_username, account_password
-        FROM password_records
-        WHERE account_owner = ?
-        "#,
-        owner
-    )
-    .fetch_all(&state.db)
-    .await;
-
-    match rows_result {
-        Ok(rows) => {
-            let status = StatusCode::OK;
-            let response = ApiResponse {
-                message: "Entries retrieved successfully".to_string(),
-                http_code: status.as_u16(),
-                data: Some(rows),
-            };
-            (status, Json(response))
-        }
+async fn serve_https(app: Router, listen: SocketAddr, tls: &TlsFiles) -> ExitCode {
+    let rustls_config = match read_pem_files(tls) {
+        Ok((cert, key)) => RustlsConfig::from_pem(cert, key).await,
+        Err(e) => Err(e),
+    };
+    let rustls_config = match rustls_config {
+        Ok(c) => c,
         Err(e) => {
-            eprintln!("DB error: {}", e);
-            let status = StatusCode::INTERNAL_SERVER_ERROR;
-            let response = ApiResponse {
-                message: "Failed to retrieve entries".to_string(),
-                http_code: status.as_u16(),
-                data: None,
-            };
-            (status, Json(response))
+            eprintln!("Failed to load TLS certificate {}: {}", tls.cert.display(), e);
+            return ExitCode::FAILURE;
         }
+    };
+
+    tokio::spawn(reload_on_sighup(rustls_config.clone(), tls.clone()));
+
+    log::info!("Server running at https://{}", listen);
+    if let Err(e) = axum_server::bind_rustls(listen, rustls_config)
+        .serve(app.into_make_service())
+        .await
+    {
+        eprintln!("Server stopped: {}", e);
+        return ExitCode::FAILURE;
     }
+
+    ExitCode::SUCCESS
 }
 
-async fn delete_entry(
-    State(state): State<AppState>,
-    Path((owner, name)): Path<(String, String)>,
-) -> (StatusCode, Json<ApiResponse<()>>) {
-    let result = sqlx::query!(
-        r#"
-        DELETE FROM password_records
-        WHERE account_owner = ? AND account_name = ?
-        "#,
-        owner,
-        name,
-    )
-    .execute(&state.db)
-    .await;
-
-    match result {
-        Ok(res) if res.rows_affected() > 0 => {
-            let status = StatusCode::OK;
-            let response = ApiResponse {
-                message: "Record deleted successfully".to_string(),
-                http_code: status.as_u16(),
-                data: None,
-            };
-            (status, Json(response))
-        }
-        Ok(_) => {
-            let status = StatusCode::NOT_FOUND;
-            let response = ApiResponse {
-                message: "No matching records found.".to_string(),
-                http_code: status.as_u16(),
-                data: None,
-            };
-            (status, Json(response))
-        }
+// Reads the certificate chain and key, rejecting files without any, so a
+// half-written renewal is never swapped in.
+fn read_pem_files(tls: &TlsFiles) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
+    let cert = std::fs::read(&tls.cert)?;
+    let key = std::fs::read(&tls.key)?;
+
+    if rustls_pemfile::certs(&mut cert.as_slice())?.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("no certificates in {}", tls.cert.display()),
+        ));
+    }
+    let has_key = rustls_pemfile::read_all(&mut key.as_slice())?.iter().any(|item| {
+        matches!(
+            item,
+            rustls_pemfile::Item::RSAKey(_) | rustls_pemfile::Item::PKCS8Key(_) | rustls_pemfile::Item::ECKey(_)
+        )
+    });
+    if !has_key {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("no private key in {}", tls.key.display()),
+        ));
+    }
+
+    Ok((cert, key))
+}
+
+// Re-reads the certificate and key on SIGHUP so renewed certificates are used
+// without a restart. Connections already open keep their certificate, and a
+// failed reload keeps serving the previous one.
+#[cfg(unix)]
+async fn reload_on_sighup(rustls_config: RustlsConfig, tls: TlsFiles) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
         Err(e) => {
-            eprintln!("Delete error: {:?}", e);
-            let status = StatusCode::INTERNAL_SERVER_ERROR;
-            let response = ApiResponse {
-                message: "Failed to delete record".to_string(),
-                http_code: status.as_u16(),
-                data: None,
-            };
-            (status, Json(response))
+            log::warn!("Cannot listen for SIGHUP, certificate reload is disabled: {}", e);
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        let result = match read_pem_files(&tls) {
+            Ok((cert, key)) => rustls_config.reload_from_pem(cert, key).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => log::info!("Reloaded TLS certificate from {}", tls.cert.display()),
+            Err(e) => log::error!("Failed to reload TLS certificate, keeping the old one: {}", e),
         }
     }
 }
 
+#[cfg(not(unix))]
+async fn reload_on_sighup(_rustls_config: RustlsConfig, _tls: TlsFiles) {}
@@ -0,0 +1,228 @@
+// Server-held keys that encrypt the account password hashes before they are
+// stored, so a copy of the database alone does not allow offline cracking.
+// The hashes are encrypted rather than HMACed because verification needs the
+// original Argon2 string back, and ?include_hash=true still hands it out.
+//
+// A sealed hash names the key it was sealed with:
+//
+//   $aps-pepper$k=<key id>$<base64 of nonce and ciphertext>
+//
+// so the current key can change while older records stay readable. Hashes
+// stored before a pepper was configured are returned as they are.
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::rngs::OsRng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+const PREFIX: &str = "$aps-pepper$k=";
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+
+// Layout of the key file, for example
+//
+//   current = "2026-10"
+//
+//   [keys]
+//   "2026-10" = "<32 random bytes, base64>"
+//   "2025-04" = "<the previous key, kept until every record is rotated>"
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyFile {
+    current: String,
+    keys: HashMap<String, String>,
+}
+
+pub struct Pepper {
+    current: String,
+    keys: HashMap<String, [u8; KEY_LEN]>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PepperError {
+    // Sealed with a key that is not in the key file
+    UnknownKey(String),
+    // Not a sealed hash, or it was altered or sealed under another key id
+    Corrupt,
+}
+
+impl fmt::Display for PepperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PepperError::UnknownKey(id) => write!(f, "no pepper key with id '{}'", id),
+            PepperError::Corrupt => write!(f, "sealed hash is malformed or was tampered with"),
+        }
+    }
+}
+
+impl std::error::Error for PepperError {}
+
+impl Pepper {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("cannot parse {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let file: KeyFile = toml::from_str(text).map_err(|e| e.to_string())?;
+
+        let mut keys = HashMap::new();
+        for (id, encoded) in file.keys {
+            if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(format!("key id '{}' may only use letters, digits, '-' and '_'", id));
+            }
+            let key: [u8; KEY_LEN] = STANDARD
+                .decode(encoded.trim())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| format!("key '{}' is not {} bytes of base64", id, KEY_LEN))?;
+            keys.insert(id, key);
+        }
+
+        if !keys.contains_key(&file.current) {
+            return Err(format!("current key '{}' is not listed under [keys]", file.current));
+        }
+
+        Ok(Pepper {
+            current: file.current,
+            keys,
+        })
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.current
+    }
+
+    // Encrypts a hash under the current key. The key id is authenticated too,
+    // so it cannot be swapped for another one.
+    pub fn seal(&self, hash: &str) -> String {
+        let cipher = XChaCha20Poly1305::new(self.keys[&self.current].as_ref().into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: hash.as_bytes(),
+            aad: self.current.as_bytes(),
+        };
+        let ciphertext = cipher
+            .encrypt(&nonce, payload)
+            .expect("XChaCha20-Poly1305 encryption does not fail for in-memory data");
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        format!("{}{}${}", PREFIX, self.current, STANDARD.encode(sealed))
+    }
+
+    // The original hash; values that were never sealed come back unchanged
+    pub fn open(&self, stored: &str) -> Result<String, PepperError> {
+        let Some(rest) = stored.strip_prefix(PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let (id, encoded) = rest.split_once('$').ok_or(PepperError::Corrupt)?;
+        let key = self.keys.get(id).ok_or_else(|| PepperError::UnknownKey(id.to_string()))?;
+
+        let sealed = STANDARD.decode(encoded).map_err(|_| PepperError::Corrupt)?;
+        if sealed.len() <= NONCE_LEN {
+            return Err(PepperError::Corrupt);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let payload = Payload {
+            msg: ciphertext,
+            aad: id.as_bytes(),
+        };
+        let hash = cipher
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| PepperError::Corrupt)?;
+        String::from_utf8(hash).map_err(|_| PepperError::Corrupt)
+    }
+
+    // Whether a stored value still has to be sealed under the current key
+    pub fn needs_rotation(&self, stored: &str) -> bool {
+        match stored.strip_prefix(PREFIX).and_then(|rest| rest.split_once('$')) {
+            Some((id, _)) => id != self.current,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K1: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+    const K2: &str = "AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI=";
+    const HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHQ$aGFzaGhhc2hoYXNoaGFzaA";
+
+    fn pepper(current: &str, keys: &[(&str, &str)]) -> Pepper {
+        let keys: Vec<String> = keys.iter().map(|(id, key)| format!("\"{}\" = \"{}\"", id, key)).collect();
+        Pepper::parse(&format!("current = \"{}\"\n[keys]\n{}\n", current, keys.join("\n"))).unwrap()
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let pepper = pepper("k1", &[("k1", K1)]);
+        let sealed = pepper.seal(HASH);
+        assert!(sealed.starts_with("$aps-pepper$k=k1$"));
+        assert!(!sealed.contains("argon2"));
+        assert_ne!(sealed, pepper.seal(HASH), "every seal uses a fresh nonce");
+        assert_eq!(pepper.open(&sealed).unwrap(), HASH);
+    }
+
+    #[test]
+    fn swapped_key_id_is_rejected() {
+        // Both ids name the same key, so only the authenticated id tells them apart
+        let pepper = pepper("k1", &[("k1", K1), ("k2", K1)]);
+        let sealed = pepper.seal(HASH);
+        let swapped = sealed.replacen("k=k1$", "k=k2$", 1);
+        assert_eq!(pepper.open(&swapped), Err(PepperError::Corrupt));
+    }
+
+    #[test]
+    fn tampered_or_malformed_values_are_corrupt() {
+        let pepper = pepper("k1", &[("k1", K1)]);
+        let sealed = pepper.seal(HASH);
+        let mut tampered = sealed.clone().into_bytes();
+        let last = tampered.len() - 3;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+
+        assert_eq!(pepper.open(&tampered), Err(PepperError::Corrupt));
+        assert_eq!(pepper.open("$aps-pepper$k=k1"), Err(PepperError::Corrupt));
+        assert_eq!(pepper.open("$aps-pepper$k=k1$AAAA"), Err(PepperError::Corrupt));
+    }
+
+    #[test]
+    fn unknown_key_is_reported() {
+        let old = pepper("k1", &[("k1", K1)]);
+        let new = pepper("k2", &[("k2", K2)]);
+        let sealed = old.seal(HASH);
+        assert_eq!(new.open(&sealed), Err(PepperError::UnknownKey("k1".to_string())));
+    }
+
+    #[test]
+    fn unsealed_values_pass_through() {
+        let pepper = pepper("k1", &[("k1", K1)]);
+        assert_eq!(pepper.open(HASH).unwrap(), HASH);
+    }
+
+    #[test]
+    fn needs_rotation_follows_the_current_key() {
+        let old = pepper("k1", &[("k1", K1)]);
+        let both = pepper("k2", &[("k1", K1), ("k2", K2)]);
+        assert!(both.needs_rotation(HASH));
+        assert!(both.needs_rotation(&old.seal(HASH)));
+        assert!(!both.needs_rotation(&both.seal(HASH)));
+    }
+
+    #[test]
+    fn key_file_is_checked() {
+        assert!(Pepper::parse("current = \"k3\"\n[keys]\nk1 = \"AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=\"\n").is_err());
+        assert!(Pepper::parse("current = \"k1\"\n[keys]\nk1 = \"AQID\"\n").is_err());
+        assert!(Pepper::parse("current = \"a b\"\n[keys]\n\"a b\" = \"AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=\"\n").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use axum::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::{RecordStore, StoreError};
+use password_common::{AddEntry, Entry, UpdateEntry, VaultHeader};
+
+// Keeps everything in process memory; all data is lost when the server stops
+#[derive(Default)]
+pub struct MemoryStore {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    last_id: u64,
+    records: Vec<Entry>,
+    vaults: HashMap<String, VaultHeader>,
+    // owner -> login hash
+    owners: HashMap<String, String>,
+    // token hash -> (owner, expiry)
+    sessions: HashMap<String, (String, DateTime<Utc>)>,
+}
+
+impl MemoryStore {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn is_match(entry: &Entry, owner: &str, id: u64) -> bool {
+    entry.id == id && entry.account_owner.as_deref() == Some(owner)
+}
+
+#[async_trait]
+impl RecordStore for MemoryStore {
+    async fn migrate(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    async fn add_entry(&self, entry: &AddEntry) -> Result<u64, StoreError> {
+        let mut inner = self.lock();
+        inner.last_id += 1;
+        let now = Utc::now();
+
+        let id = inner.last_id;
+        inner.records.push(Entry {
+            id,
+            account_owner: Some(entry.owner.clone()),
+            account_name: Some(entry.name.clone()),
+            account_username: Some(entry.username.clone()),
+            account_password: Some(entry.password.clone()),
+            account_ciphertext: Some(entry.ciphertext.clone()),
+            account_nonce: Some(entry.nonce.clone()),
+            created_at: now,
+            updated_at: now,
+        });
+
+        Ok(id)
+    }
+
+    async fn list_entries(&self, owner: &str) -> Result<Vec<Entry>, StoreError> {
+        let inner = self.lock();
+        Ok(inner
+            .records
+            .iter()
+            .filter(|e| e.account_owner.as_deref() == Some(owner))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_entry(&self, owner: &str, id: u64) -> Result<Option<Entry>, StoreError> {
+        let inner = self.lock();
+        Ok(inner.records.iter().find(|e| is_match(e, owner, id)).cloned())
+    }
+
+    async fn update_entry(&self, owner: &str, id: u64, update: &UpdateEntry) -> Result<bool, StoreError> {
+        let mut inner = self.lock();
+        let entry = match inner.records.iter_mut().find(|e| is_match(e, owner, id)) {
+            Some(e) => e,
+            None => return Ok(false),
+        };
+
+        if let Some(name) = &update.name {
+            entry.account_name = Some(name.clone());
+        }
+        if let Some(username) = &update.username {
+            entry.account_username = Some(username.clone());
+        }
+        if let Some(password) = &update.password {
+            entry.account_password = Some(password.clone());
+        }
+        if let Some(ciphertext) = &update.ciphertext {
+            entry.account_ciphertext = Some(ciphertext.clone());
+        }
+        if let Some(nonce) = &update.nonce {
+            entry.account_nonce = Some(nonce.clone());
+        }
+        entry.updated_at = Utc::now();
+
+        Ok(true)
+    }
+
+    async fn delete_entry(&self, owner: &str, id: u64) -> Result<bool, StoreError> {
+        let mut inner = self.lock();
+        let before = inner.records.len();
+        inner.records.retain(|e| !is_match(e, owner, id));
+        Ok(inner.records.len() < before)
+    }
+
+    async fn delete_entries_by_name(&self, owner: &str, name: &str) -> Result<u64, StoreError> {
+        let mut inner = self.lock();
+        let before = inner.records.len();
+        inner.records.retain(|e| {
+            e.account_owner.as_deref() != Some(owner) || e.account_name.as_deref() != Some(name)
+        });
+        Ok((before - inner.records.len()) as u64)
+    }
+
+    async fn password_hashes(&self) -> Result<Vec<(u64, String)>, StoreError> {
+        let inner = self.lock();
+        Ok(inner
+            .records
+            .iter()
+            .filter_map(|e| Some((e.id, e.account_password.clone()?)))
+            .collect())
+    }
+
+    async fn replace_password_hash(&self, id: u64, old: &str, new: &str) -> Result<bool, StoreError> {
+        let mut inner = self.lock();
+        match inner
+            .records
+            .iter_mut()
+            .find(|e| e.id == id && e.account_password.as_deref() == Some(old))
+        {
+            Some(entry) => {
+                entry.account_password = Some(new.to_string());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn get_vault(&self, owner: &str) -> Result<Option<VaultHeader>, StoreError> {
+        Ok(self.lock().vaults.get(owner).cloned())
+    }
+
+    async fn register_owner(&self, login_hash: &str, vault: &VaultHeader) -> Result<(), StoreError> {
+        let mut inner = self.lock();
+        if inner.owners.contains_key(&vault.owner) || inner.vaults.contains_key(&vault.owner) {
+            return Err(StoreError::Conflict);
+        }
+
+        inner.owners.insert(vault.owner.clone(), login_hash.to_string());
+        inner.vaults.insert(vault.owner.clone(), vault.clone());
+        Ok(())
+    }
+
+    async fn get_login_hash(&self, owner: &str) -> Result<Option<String>, StoreError> {
+        Ok(self.lock().owners.get(owner).cloned())
+    }
+
+    async fn create_session(&self, token_hash: &str, owner: &str, ttl_seconds: u64) -> Result<(), StoreError> {
+        let mut inner = self.lock();
+        let now = Utc::now();
+        inner.sessions.retain(|_, (_, expires_at)| *expires_at > now);
+
+        let expires_at = now + Duration::seconds(ttl_seconds as i64);
+        inner
+            .sessions
+            .insert(token_hash.to_string(), (owner.to_string(), expires_at));
+        Ok(())
+    }
+
+    async fn session_owner(&self, token_hash: &str) -> Result<Option<String>, StoreError> {
+        let inner = self.lock();
+        Ok(inner
+            .sessions
+            .get(token_hash)
+            .filter(|(_, expires_at)| *expires_at > Utc::now())
+            .map(|(owner, _)| owner.clone()))
+    }
+}
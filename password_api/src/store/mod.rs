@@ -0,0 +1,139 @@
+use axum::async_trait;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::pepper::PepperError;
+use password_common::{AddEntry, Entry, UpdateEntry, VaultHeader};
+
+mod memory;
+mod mysql;
+mod peppered;
+mod sqlite;
+
+pub use memory::MemoryStore;
+pub use mysql::MySqlStore;
+pub use peppered::{PepperedStore, Rotation};
+pub use sqlite::SqliteStore;
+
+#[derive(Debug)]
+pub enum StoreError {
+    // A row with the same key already exists
+    Conflict,
+    Database(sqlx::Error),
+    Migrate(sqlx::migrate::MigrateError),
+    // A stored hash could not be unsealed, which points at the server's
+    // pepper key file rather than at the record
+    Pepper { id: u64, error: PepperError },
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Conflict => write!(f, "record already exists"),
+            StoreError::Database(e) => write!(f, "database error: {}", e),
+            StoreError::Migrate(e) => write!(f, "migration error: {}", e),
+            StoreError::Pepper { id, error } => write!(f, "cannot open the password hash of record {}: {}", id, error),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::Database(ref db) if db.is_unique_violation() => StoreError::Conflict,
+            e => StoreError::Database(e),
+        }
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for StoreError {
+    fn from(e: sqlx::migrate::MigrateError) -> Self {
+        StoreError::Migrate(e)
+    }
+}
+
+// Everything password_api persists. Lookups that take an owner only ever
+// see that owner's rows, so handlers cannot leak records across owners.
+#[async_trait]
+pub trait RecordStore: Send + Sync {
+    // Bring the schema up to date
+    async fn migrate(&self) -> Result<(), StoreError>;
+
+    // Returns the id of the new record
+    async fn add_entry(&self, entry: &AddEntry) -> Result<u64, StoreError>;
+    async fn list_entries(&self, owner: &str) -> Result<Vec<Entry>, StoreError>;
+    async fn get_entry(&self, owner: &str, id: u64) -> Result<Option<Entry>, StoreError>;
+    // The same with account_password left out, for callers that do not need
+    // it; a wrapping store may then skip work it does for the hashes
+    async fn list_entries_without_hashes(&self, owner: &str) -> Result<Vec<Entry>, StoreError> {
+        Ok(self.list_entries(owner).await?.into_iter().map(without_hash).collect())
+    }
+    async fn get_entry_without_hash(&self, owner: &str, id: u64) -> Result<Option<Entry>, StoreError> {
+        Ok(self.get_entry(owner, id).await?.map(without_hash))
+    }
+    // The bool results report whether a matching record existed
+    async fn update_entry(&self, owner: &str, id: u64, update: &UpdateEntry) -> Result<bool, StoreError>;
+    async fn delete_entry(&self, owner: &str, id: u64) -> Result<bool, StoreError>;
+    // Removes every record with this name, returning how many were deleted
+    async fn delete_entries_by_name(&self, owner: &str, name: &str) -> Result<u64, StoreError>;
+
+    // Id and stored password hash of every record of every owner, for maintenance
+    async fn password_hashes(&self) -> Result<Vec<(u64, String)>, StoreError>;
+    // Sets the stored hash only while it is still `old`, so a concurrent
+    // update is never overwritten. Leaves updated_at alone.
+    async fn replace_password_hash(&self, id: u64, old: &str, new: &str) -> Result<bool, StoreError>;
+
+    async fn get_vault(&self, owner: &str) -> Result<Option<VaultHeader>, StoreError>;
+    // Creates the owner account and its vault header together
+    async fn register_owner(&self, login_hash: &str, vault: &VaultHeader) -> Result<(), StoreError>;
+    async fn get_login_hash(&self, owner: &str) -> Result<Option<String>, StoreError>;
+
+    // Also clears out expired sessions
+    async fn create_session(&self, token_hash: &str, owner: &str, ttl_seconds: u64) -> Result<(), StoreError>;
+    // The owner of an unexpired session
+    async fn session_owner(&self, token_hash: &str) -> Result<Option<String>, StoreError>;
+}
+
+pub fn without_hash(mut entry: Entry) -> Entry {
+    entry.account_password = None;
+    entry
+}
+
+pub enum Backend {
+    MySql(String),
+    Sqlite(String),
+    Memory,
+}
+
+// Connection pool settings for the database backends
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    // How long a request waits for a free connection before failing
+    pub acquire_timeout: Duration,
+    // Unused connections are closed after this long; None keeps them open
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            max_connections: 5,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+        }
+    }
+}
+
+pub async fn open(backend: &Backend, pool: &PoolConfig) -> Result<Arc<dyn RecordStore>, StoreError> {
+    let store: Arc<dyn RecordStore> = match backend {
+        Backend::MySql(url) => Arc::new(MySqlStore::connect(url, pool).await?),
+        Backend::Sqlite(url) => Arc::new(SqliteStore::connect(url, pool).await?),
+        Backend::Memory => Arc::new(MemoryStore::default()),
+    };
+
+    Ok(store)
+}
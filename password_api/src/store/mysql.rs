@@ -0,0 +1,262 @@
+use axum::async_trait;
+use sqlx::mysql::{MySqlPool, MySqlPoolOptions, MySqlRow};
+use sqlx::Row;
+
+use super::{PoolConfig, RecordStore, StoreError};
+use password_common::{AddEntry, Entry, UpdateEntry, VaultHeader};
+
+const ENTRY_COLUMNS: &str = "id, account_owner, account_name, account_username, account_password, \
+     account_ciphertext, account_nonce, created_at, updated_at";
+
+pub struct MySqlStore {
+    db: MySqlPool,
+}
+
+impl MySqlStore {
+    pub async fn connect(url: &str, pool: &PoolConfig) -> Result<Self, StoreError> {
+        let db = MySqlPoolOptions::new()
+            .max_connections(pool.max_connections)
+            .acquire_timeout(pool.acquire_timeout)
+            .idle_timeout(pool.idle_timeout)
+            .connect(url)
+            .await?;
+
+        Ok(MySqlStore { db })
+    }
+}
+
+fn entry_from_row(row: &MySqlRow) -> Result<Entry, sqlx::Error> {
+    Ok(Entry {
+        id: row.try_get("id")?,
+        account_owner: row.try_get("account_owner")?,
+        account_name: row.try_get("account_name")?,
+        account_username: row.try_get("account_username")?,
+        account_password: row.try_get("account_password")?,
+        account_ciphertext: row.try_get("account_ciphertext")?,
+        account_nonce: row.try_get("account_nonce")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+#[async_trait]
+impl RecordStore for MySqlStore {
+    async fn migrate(&self) -> Result<(), StoreError> {
+        sqlx::migrate!("./migrations/mysql").run(&self.db).await?;
+        Ok(())
+    }
+
+    async fn add_entry(&self, entry: &AddEntry) -> Result<u64, StoreError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO password_records (account_owner, account_name, account_username, account_password, account_ciphertext, account_nonce)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&entry.owner)
+        .bind(&entry.name)
+        .bind(&entry.username)
+        .bind(&entry.password)
+        .bind(&entry.ciphertext)
+        .bind(&entry.nonce)
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.last_insert_id())
+    }
+
+    async fn list_entries(&self, owner: &str) -> Result<Vec<Entry>, StoreError> {
+        let sql = format!(
+            "SELECT {} FROM password_records WHERE account_owner = ? ORDER BY id",
+            ENTRY_COLUMNS
+        );
+        let rows = sqlx::query(&sql).bind(owner).fetch_all(&self.db).await?;
+
+        Ok(rows.iter().map(entry_from_row).collect::<Result<_, _>>()?)
+    }
+
+    async fn get_entry(&self, owner: &str, id: u64) -> Result<Option<Entry>, StoreError> {
+        let sql = format!(
+            "SELECT {} FROM password_records WHERE id = ? AND account_owner = ?",
+            ENTRY_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(row.as_ref().map(entry_from_row).transpose()?)
+    }
+
+    async fn update_entry(&self, owner: &str, id: u64, update: &UpdateEntry) -> Result<bool, StoreError> {
+        // A single statement, so the record is never left half updated
+        let result = sqlx::query(
+            r#"
+            UPDATE password_records
+            SET account_name = COALESCE(?, account_name),
+                account_username = COALESCE(?, account_username),
+                account_password = COALESCE(?, account_password),
+                account_ciphertext = COALESCE(?, account_ciphertext),
+                account_nonce = COALESCE(?, account_nonce)
+            WHERE id = ? AND account_owner = ?
+            "#,
+        )
+        .bind(&update.name)
+        .bind(&update.username)
+        .bind(&update.password)
+        .bind(&update.ciphertext)
+        .bind(&update.nonce)
+        .bind(id)
+        .bind(owner)
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_entry(&self, owner: &str, id: u64) -> Result<bool, StoreError> {
+        let result = sqlx::query("DELETE FROM password_records WHERE id = ? AND account_owner = ?")
+            .bind(id)
+            .bind(owner)
+            .execute(&self.db)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_entries_by_name(&self, owner: &str, name: &str) -> Result<u64, StoreError> {
+        let result = sqlx::query("DELETE FROM password_records WHERE account_owner = ? AND account_name = ?")
+            .bind(owner)
+            .bind(name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn password_hashes(&self) -> Result<Vec<(u64, String)>, StoreError> {
+        let rows = sqlx::query_as(
+            "SELECT id, account_password FROM password_records WHERE account_password IS NOT NULL ORDER BY id",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn replace_password_hash(&self, id: u64, old: &str, new: &str) -> Result<bool, StoreError> {
+        // Assigning updated_at to itself stops ON UPDATE CURRENT_TIMESTAMP
+        let result = sqlx::query(
+            r#"
+            UPDATE password_records
+            SET account_password = ?, updated_at = updated_at
+            WHERE id = ? AND account_password = ?
+            "#,
+        )
+        .bind(new)
+        .bind(id)
+        .bind(old)
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_vault(&self, owner: &str) -> Result<Option<VaultHeader>, StoreError> {
+        let row = sqlx::query(
+            r#"
+            SELECT owner, kdf_salt, kdf_memory_kib, kdf_iterations, kdf_parallelism, key_check_ciphertext, key_check_nonce
+            FROM vault_metadata
+            WHERE owner = ?
+            "#,
+        )
+        .bind(owner)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let header = match row {
+            Some(row) => Some(VaultHeader {
+                owner: row.try_get("owner")?,
+                kdf_salt: row.try_get("kdf_salt")?,
+                kdf_memory_kib: row.try_get("kdf_memory_kib")?,
+                kdf_iterations: row.try_get("kdf_iterations")?,
+                kdf_parallelism: row.try_get("kdf_parallelism")?,
+                key_check_ciphertext: row.try_get("key_check_ciphertext")?,
+                key_check_nonce: row.try_get("key_check_nonce")?,
+            }),
+            None => None,
+        };
+
+        Ok(header)
+    }
+
+    async fn register_owner(&self, login_hash: &str, vault: &VaultHeader) -> Result<(), StoreError> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query("INSERT INTO owners (owner, login_hash) VALUES (?, ?)")
+            .bind(&vault.owner)
+            .bind(login_hash)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO vault_metadata (owner, kdf_salt, kdf_memory_kib, kdf_iterations, kdf_parallelism, key_check_ciphertext, key_check_nonce)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&vault.owner)
+        .bind(&vault.kdf_salt)
+        .bind(vault.kdf_memory_kib)
+        .bind(vault.kdf_iterations)
+        .bind(vault.kdf_parallelism)
+        .bind(&vault.key_check_ciphertext)
+        .bind(&vault.key_check_nonce)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_login_hash(&self, owner: &str) -> Result<Option<String>, StoreError> {
+        let hash = sqlx::query_scalar("SELECT login_hash FROM owners WHERE owner = ?")
+            .bind(owner)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(hash)
+    }
+
+    async fn create_session(&self, token_hash: &str, owner: &str, ttl_seconds: u64) -> Result<(), StoreError> {
+        sqlx::query("DELETE FROM sessions WHERE expires_at <= UTC_TIMESTAMP()")
+            .execute(&self.db)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (token_hash, owner, expires_at)
+            VALUES (?, ?, DATE_ADD(UTC_TIMESTAMP(), INTERVAL ? SECOND))
+            "#,
+        )
+        .bind(token_hash)
+        .bind(owner)
+        .bind(ttl_seconds)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn session_owner(&self, token_hash: &str) -> Result<Option<String>, StoreError> {
+        let owner = sqlx::query_scalar(
+            "SELECT owner FROM sessions WHERE token_hash = ? AND expires_at > UTC_TIMESTAMP()",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(owner)
+    }
+}
@@ -0,0 +1,276 @@
+use axum::async_trait;
+use std::sync::Arc;
+
+use super::{RecordStore, StoreError};
+use crate::pepper::Pepper;
+use password_common::{AddEntry, Entry, UpdateEntry, VaultHeader};
+
+// Wraps another store so account password hashes are sealed with the pepper
+// on the way in and opened on the way out. Everything else passes through.
+pub struct PepperedStore {
+    inner: Arc<dyn RecordStore>,
+    pepper: Pepper,
+}
+
+// How a rotation went
+#[derive(Debug, Default)]
+pub struct Rotation {
+    pub resealed: u64,
+    // Records that could not be opened and were left alone
+    pub failed: u64,
+}
+
+impl PepperedStore {
+    pub fn new(inner: Arc<dyn RecordStore>, pepper: Pepper) -> Self {
+        PepperedStore { inner, pepper }
+    }
+
+    // A hash that cannot be opened fails the request. Passing the record on
+    // without it would make a missing or wrong key look like a damaged record.
+    fn open_entry(&self, mut entry: Entry) -> Result<Entry, StoreError> {
+        if let Some(stored) = &entry.account_password {
+            let hash = self
+                .pepper
+                .open(stored)
+                .map_err(|error| StoreError::Pepper { id: entry.id, error })?;
+            entry.account_password = Some(hash);
+        }
+        Ok(entry)
+    }
+
+    // Seals every hash that is unpeppered or under an older key with the
+    // current key, so retired keys can then be removed from the key file
+    pub async fn rotate(&self) -> Result<Rotation, StoreError> {
+        let mut rotation = Rotation::default();
+
+        for (id, stored) in self.inner.password_hashes().await? {
+            if !self.pepper.needs_rotation(&stored) {
+                continue;
+            }
+            let hash = match self.pepper.open(&stored) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    log::error!("Cannot open the password hash of record {}: {}", id, e);
+                    rotation.failed += 1;
+                    continue;
+                }
+            };
+
+            // A record changed or deleted meanwhile already has a current hash or none
+            if self
+                .inner
+                .replace_password_hash(id, &stored, &self.pepper.seal(&hash))
+                .await?
+            {
+                rotation.resealed += 1;
+            }
+        }
+
+        Ok(rotation)
+    }
+}
+
+#[async_trait]
+impl RecordStore for PepperedStore {
+    async fn migrate(&self) -> Result<(), StoreError> {
+        self.inner.migrate().await
+    }
+
+    async fn add_entry(&self, entry: &AddEntry) -> Result<u64, StoreError> {
+        let sealed = AddEntry {
+            password: self.pepper.seal(&entry.password),
+            ..entry.clone()
+        };
+        self.inner.add_entry(&sealed).await
+    }
+
+    async fn list_entries(&self, owner: &str) -> Result<Vec<Entry>, StoreError> {
+        let entries = self.inner.list_entries(owner).await?;
+        entries.into_iter().map(|e| self.open_entry(e)).collect()
+    }
+
+    async fn get_entry(&self, owner: &str, id: u64) -> Result<Option<Entry>, StoreError> {
+        let entry = self.inner.get_entry(owner, id).await?;
+        entry.map(|e| self.open_entry(e)).transpose()
+    }
+
+    // Nothing to open, so a missing key does not hide the records
+    async fn list_entries_without_hashes(&self, owner: &str) -> Result<Vec<Entry>, StoreError> {
+        self.inner.list_entries_without_hashes(owner).await
+    }
+
+    async fn get_entry_without_hash(&self, owner: &str, id: u64) -> Result<Option<Entry>, StoreError> {
+        self.inner.get_entry_without_hash(owner, id).await
+    }
+
+    async fn update_entry(&self, owner: &str, id: u64, update: &UpdateEntry) -> Result<bool, StoreError> {
+        let sealed = UpdateEntry {
+            password: update.password.as_deref().map(|p| self.pepper.seal(p)),
+            ..update.clone()
+        };
+        self.inner.update_entry(owner, id, &sealed).await
+    }
+
+    async fn delete_entry(&self, owner: &str, id: u64) -> Result<bool, StoreError> {
+        self.inner.delete_entry(owner, id).await
+    }
+
+    async fn delete_entries_by_name(&self, owner: &str, name: &str) -> Result<u64, StoreError> {
+        self.inner.delete_entries_by_name(owner, name).await
+    }
+
+    // As stored, still sealed
+    async fn password_hashes(&self) -> Result<Vec<(u64, String)>, StoreError> {
+        self.inner.password_hashes().await
+    }
+
+    async fn replace_password_hash(&self, id: u64, old: &str, new: &str) -> Result<bool, StoreError> {
+        self.inner.replace_password_hash(id, old, new).await
+    }
+
+    async fn get_vault(&self, owner: &str) -> Result<Option<VaultHeader>, StoreError> {
+        self.inner.get_vault(owner).await
+    }
+
+    async fn register_owner(&self, login_hash: &str, vault: &VaultHeader) -> Result<(), StoreError> {
+        self.inner.register_owner(login_hash, vault).await
+    }
+
+    async fn get_login_hash(&self, owner: &str) -> Result<Option<String>, StoreError> {
+        self.inner.get_login_hash(owner).await
+    }
+
+    async fn create_session(&self, token_hash: &str, owner: &str, ttl_seconds: u64) -> Result<(), StoreError> {
+        self.inner.create_session(token_hash, owner, ttl_seconds).await
+    }
+
+    async fn session_owner(&self, token_hash: &str) -> Result<Option<String>, StoreError> {
+        self.inner.session_owner(token_hash).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    const HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHQ$aGFzaGhhc2hoYXNoaGFzaA";
+    const K1: &str = "\"k1\" = \"AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=\"";
+    const K2: &str = "\"k2\" = \"AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI=\"";
+
+    fn pepper(current: &str, keys: &[&str]) -> Pepper {
+        Pepper::parse(&format!("current = \"{}\"\n[keys]\n{}\n", current, keys.join("\n"))).unwrap()
+    }
+
+    fn new_entry(hash: &str) -> AddEntry {
+        AddEntry {
+            owner: "alice".to_string(),
+            name: "mail".to_string(),
+            username: "alice@example.com".to_string(),
+            password: hash.to_string(),
+            ciphertext: "Y2lwaGVy".to_string(),
+            nonce: "bm9uY2U=".to_string(),
+        }
+    }
+
+    async fn stored(inner: &MemoryStore, id: u64) -> String {
+        inner
+            .password_hashes()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|(i, _)| *i == id)
+            .map(|(_, hash)| hash)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn hashes_are_sealed_at_rest_and_opened_on_read() {
+        let inner = Arc::new(MemoryStore::default());
+        let store = PepperedStore::new(inner.clone(), pepper("k1", &[K1]));
+        let id = store.add_entry(&new_entry(HASH)).await.unwrap();
+
+        assert!(stored(&inner, id).await.starts_with("$aps-pepper$k=k1$"));
+        let entry = store.get_entry("alice", id).await.unwrap().unwrap();
+        assert_eq!(entry.account_password.as_deref(), Some(HASH));
+    }
+
+    #[tokio::test]
+    async fn missing_key_is_a_store_error_but_hash_free_reads_work() {
+        let inner = Arc::new(MemoryStore::default());
+        let id = PepperedStore::new(inner.clone(), pepper("k1", &[K1]))
+            .add_entry(&new_entry(HASH))
+            .await
+            .unwrap();
+        let store = PepperedStore::new(inner, pepper("k2", &[K2]));
+
+        assert!(matches!(
+            store.get_entry("alice", id).await,
+            Err(StoreError::Pepper { id: failed, .. }) if failed == id
+        ));
+        assert!(store.list_entries("alice").await.is_err());
+        assert_eq!(store.list_entries_without_hashes("alice").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rotate_reseals_old_and_unpeppered_hashes() {
+        let inner = Arc::new(MemoryStore::default());
+        let unpeppered = inner.add_entry(&new_entry(HASH)).await.unwrap();
+        let old = PepperedStore::new(inner.clone(), pepper("k1", &[K1]))
+            .add_entry(&new_entry(HASH))
+            .await
+            .unwrap();
+        let store = PepperedStore::new(inner.clone(), pepper("k2", &[K1, K2]));
+        let current = store.add_entry(&new_entry(HASH)).await.unwrap();
+        let current_sealed = stored(&inner, current).await;
+
+        let rotation = store.rotate().await.unwrap();
+        assert_eq!((rotation.resealed, rotation.failed), (2, 0));
+        for id in [unpeppered, old] {
+            assert!(stored(&inner, id).await.starts_with("$aps-pepper$k=k2$"));
+            let entry = store.get_entry("alice", id).await.unwrap().unwrap();
+            assert_eq!(entry.account_password.as_deref(), Some(HASH));
+        }
+        // Already under the current key, so left untouched
+        assert_eq!(stored(&inner, current).await, current_sealed);
+
+        let again = store.rotate().await.unwrap();
+        assert_eq!((again.resealed, again.failed), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn rotate_leaves_records_it_cannot_open() {
+        let inner = Arc::new(MemoryStore::default());
+        let id = PepperedStore::new(inner.clone(), pepper("k1", &[K1]))
+            .add_entry(&new_entry(HASH))
+            .await
+            .unwrap();
+        let before = stored(&inner, id).await;
+
+        let rotation = PepperedStore::new(inner.clone(), pepper("k2", &[K2])).rotate().await.unwrap();
+        assert_eq!((rotation.resealed, rotation.failed), (0, 1));
+        assert_eq!(stored(&inner, id).await, before);
+    }
+
+    #[tokio::test]
+    async fn replace_password_hash_only_swaps_the_expected_value() {
+        let inner = Arc::new(MemoryStore::default());
+        let store = PepperedStore::new(inner.clone(), pepper("k1", &[K1]));
+        let id = store.add_entry(&new_entry(HASH)).await.unwrap();
+        let sealed = stored(&inner, id).await;
+
+        // The record changed after rotation read it, so the stale value loses
+        let update = UpdateEntry {
+            password: Some("$argon2id$changed".to_string()),
+            ..UpdateEntry::default()
+        };
+        assert!(store.update_entry("alice", id, &update).await.unwrap());
+        let changed = stored(&inner, id).await;
+        assert!(!store.replace_password_hash(id, &sealed, "replacement").await.unwrap());
+        assert_eq!(stored(&inner, id).await, changed);
+
+        assert!(store.replace_password_hash(id, &changed, "replacement").await.unwrap());
+        assert_eq!(stored(&inner, id).await, "replacement");
+        assert!(!store.replace_password_hash(id + 1, "replacement", "other").await.unwrap());
+    }
+}
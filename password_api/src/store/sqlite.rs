@@ -0,0 +1,368 @@
+use axum::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+use std::str::FromStr;
+
+use super::{PoolConfig, RecordStore, StoreError};
+use password_common::{AddEntry, Entry, UpdateEntry, VaultHeader};
+
+const ENTRY_COLUMNS: &str = "id, account_owner, account_name, account_username, account_password, \
+     account_ciphertext, account_nonce, created_at, updated_at";
+
+// File-based storage that needs no server; the database file is created on first use
+pub struct SqliteStore {
+    db: SqlitePool,
+}
+
+impl SqliteStore {
+    pub async fn connect(url: &str, pool: &PoolConfig) -> Result<Self, StoreError> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        let db = SqlitePoolOptions::new()
+            .max_connections(pool.max_connections)
+            .acquire_timeout(pool.acquire_timeout)
+            .idle_timeout(pool.idle_timeout)
+            .connect_with(options)
+            .await?;
+
+        Ok(SqliteStore { db })
+    }
+}
+
+// SQLite integers are signed; ids are never negative
+fn entry_from_row(row: &SqliteRow) -> Result<Entry, sqlx::Error> {
+    Ok(Entry {
+        id: row.try_get::<i64, _>("id")? as u64,
+        account_owner: row.try_get("account_owner")?,
+        account_name: row.try_get("account_name")?,
+        account_username: row.try_get("account_username")?,
+        account_password: row.try_get("account_password")?,
+        account_ciphertext: row.try_get("account_ciphertext")?,
+        account_nonce: row.try_get("account_nonce")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+#[async_trait]
+impl RecordStore for SqliteStore {
+    async fn migrate(&self) -> Result<(), StoreError> {
+        sqlx::migrate!("./migrations/sqlite").run(&self.db).await?;
+        Ok(())
+    }
+
+    async fn add_entry(&self, entry: &AddEntry) -> Result<u64, StoreError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO password_records (account_owner, account_name, account_username, account_password, account_ciphertext, account_nonce)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&entry.owner)
+        .bind(&entry.name)
+        .bind(&entry.username)
+        .bind(&entry.password)
+        .bind(&entry.ciphertext)
+        .bind(&entry.nonce)
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.last_insert_rowid() as u64)
+    }
+
+    async fn list_entries(&self, owner: &str) -> Result<Vec<Entry>, StoreError> {
+        let sql = format!(
+            "SELECT {} FROM password_records WHERE account_owner = ? ORDER BY id",
+            ENTRY_COLUMNS
+        );
+        let rows = sqlx::query(&sql).bind(owner).fetch_all(&self.db).await?;
+
+        Ok(rows.iter().map(entry_from_row).collect::<Result<_, _>>()?)
+    }
+
+    async fn get_entry(&self, owner: &str, id: u64) -> Result<Option<Entry>, StoreError> {
+        let sql = format!(
+            "SELECT {} FROM password_records WHERE id = ? AND account_owner = ?",
+            ENTRY_COLUMNS
+        );
+        let row = sqlx::query(&sql)
+            .bind(id as i64)
+            .bind(owner)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(row.as_ref().map(entry_from_row).transpose()?)
+    }
+
+    async fn update_entry(&self, owner: &str, id: u64, update: &UpdateEntry) -> Result<bool, StoreError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE password_records
+            SET account_name = COALESCE(?, account_name),
+                account_username = COALESCE(?, account_username),
+                account_password = COALESCE(?, account_password),
+                account_ciphertext = COALESCE(?, account_ciphertext),
+                account_nonce = COALESCE(?, account_nonce),
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND account_owner = ?
+            "#,
+        )
+        .bind(&update.name)
+        .bind(&update.username)
+        .bind(&update.password)
+        .bind(&update.ciphertext)
+        .bind(&update.nonce)
+        .bind(id as i64)
+        .bind(owner)
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_entry(&self, owner: &str, id: u64) -> Result<bool, StoreError> {
+        let result = sqlx::query("DELETE FROM password_records WHERE id = ? AND account_owner = ?")
+            .bind(id as i64)
+            .bind(owner)
+            .execute(&self.db)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_entries_by_name(&self, owner: &str, name: &str) -> Result<u64, StoreError> {
+        let result = sqlx::query("DELETE FROM password_records WHERE account_owner = ? AND account_name = ?")
+            .bind(owner)
+            .bind(name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn password_hashes(&self) -> Result<Vec<(u64, String)>, StoreError> {
+        let rows: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT id, account_password FROM password_records WHERE account_password IS NOT NULL ORDER BY id",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows.into_iter().map(|(id, hash)| (id as u64, hash)).collect())
+    }
+
+    async fn replace_password_hash(&self, id: u64, old: &str, new: &str) -> Result<bool, StoreError> {
+        let result = sqlx::query("UPDATE password_records SET account_password = ? WHERE id = ? AND account_password = ?")
+            .bind(new)
+            .bind(id as i64)
+            .bind(old)
+            .execute(&self.db)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_vault(&self, owner: &str) -> Result<Option<VaultHeader>, StoreError> {
+        let row = sqlx::query(
+            r#"
+            SELECT owner, kdf_salt, kdf_memory_kib, kdf_iterations, kdf_parallelism, key_check_ciphertext, key_check_nonce
+            FROM vault_metadata
+            WHERE owner = ?
+            "#,
+        )
+        .bind(owner)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let header = match row {
+            Some(row) => Some(VaultHeader {
+                owner: row.try_get("owner")?,
+                kdf_salt: row.try_get("kdf_salt")?,
+                kdf_memory_kib: row.try_get("kdf_memory_kib")?,
+                kdf_iterations: row.try_get("kdf_iterations")?,
+                kdf_parallelism: row.try_get("kdf_parallelism")?,
+                key_check_ciphertext: row.try_get("key_check_ciphertext")?,
+                key_check_nonce: row.try_get("key_check_nonce")?,
+            }),
+            None => None,
+        };
+
+        Ok(header)
+    }
+
+    async fn register_owner(&self, login_hash: &str, vault: &VaultHeader) -> Result<(), StoreError> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query("INSERT INTO owners (owner, login_hash) VALUES (?, ?)")
+            .bind(&vault.owner)
+            .bind(login_hash)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO vault_metadata (owner, kdf_salt, kdf_memory_kib, kdf_iterations, kdf_parallelism, key_check_ciphertext, key_check_nonce)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&vault.owner)
+        .bind(&vault.kdf_salt)
+        .bind(vault.kdf_memory_kib)
+        .bind(vault.kdf_iterations)
+        .bind(vault.kdf_parallelism)
+        .bind(&vault.key_check_ciphertext)
+        .bind(&vault.key_check_nonce)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_login_hash(&self, owner: &str) -> Result<Option<String>, StoreError> {
+        let hash = sqlx::query_scalar("SELECT login_hash FROM owners WHERE owner = ?")
+            .bind(owner)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(hash)
+    }
+
+    async fn create_session(&self, token_hash: &str, owner: &str, ttl_seconds: u64) -> Result<(), StoreError> {
+        sqlx::query("DELETE FROM sessions WHERE expires_at <= datetime('now')")
+            .execute(&self.db)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (token_hash, owner, expires_at)
+            VALUES (?, ?, datetime('now', '+' || ? || ' seconds'))
+            "#,
+        )
+        .bind(token_hash)
+        .bind(owner)
+        .bind(ttl_seconds as i64)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn session_owner(&self, token_hash: &str) -> Result<Option<String>, StoreError> {
+        let owner = sqlx::query_scalar(
+            "SELECT owner FROM sessions WHERE token_hash = ? AND expires_at > datetime('now')",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(owner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // Every connection to :memory: opens a database of its own, so the pool
+    // keeps exactly one and never lets it go
+    async fn store() -> SqliteStore {
+        let pool = PoolConfig {
+            max_connections: 1,
+            acquire_timeout: Duration::from_secs(5),
+            idle_timeout: None,
+        };
+        let store = SqliteStore::connect("sqlite::memory:", &pool).await.unwrap();
+        store.migrate().await.unwrap();
+        store
+    }
+
+    fn new_entry(owner: &str, name: &str) -> AddEntry {
+        AddEntry {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            username: "user".to_string(),
+            password: "hash".to_string(),
+            ciphertext: "Y2lwaGVy".to_string(),
+            nonce: "bm9uY2U=".to_string(),
+        }
+    }
+
+    fn vault(owner: &str) -> VaultHeader {
+        VaultHeader {
+            owner: owner.to_string(),
+            kdf_salt: "c2FsdA==".to_string(),
+            kdf_memory_kib: 19456,
+            kdf_iterations: 2,
+            kdf_parallelism: 1,
+            key_check_ciphertext: "Y2hlY2s=".to_string(),
+            key_check_nonce: "bm9uY2U=".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn migrations_can_run_again() {
+        let store = store().await;
+        store.migrate().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn entries_are_added_read_updated_and_deleted() {
+        let store = store().await;
+        let first = store.add_entry(&new_entry("alice", "mail")).await.unwrap();
+        let second = store.add_entry(&new_entry("alice", "mail")).await.unwrap();
+        assert_ne!(first, second);
+
+        let entry = store.get_entry("alice", first).await.unwrap().unwrap();
+        assert_eq!(entry.account_name.as_deref(), Some("mail"));
+        assert_eq!(entry.account_password.as_deref(), Some("hash"));
+        assert_eq!(entry.account_ciphertext.as_deref(), Some("Y2lwaGVy"));
+        assert!(store.get_entry("bob", first).await.unwrap().is_none());
+        let ids: Vec<u64> = store.list_entries("alice").await.unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![first, second]);
+
+        let rename = UpdateEntry {
+            name: Some("work mail".to_string()),
+            ..UpdateEntry::default()
+        };
+        assert!(store.update_entry("alice", first, &rename).await.unwrap());
+        assert!(!store.update_entry("bob", first, &rename).await.unwrap());
+        let entry = store.get_entry("alice", first).await.unwrap().unwrap();
+        assert_eq!(entry.account_name.as_deref(), Some("work mail"));
+        assert_eq!(entry.account_username.as_deref(), Some("user"));
+        assert_eq!(entry.account_password.as_deref(), Some("hash"));
+
+        assert!(!store.delete_entry("bob", first).await.unwrap());
+        assert!(store.delete_entry("alice", first).await.unwrap());
+        assert!(store.get_entry("alice", first).await.unwrap().is_none());
+        assert_eq!(store.delete_entries_by_name("alice", "mail").await.unwrap(), 1);
+        assert!(store.list_entries("alice").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn owners_register_once() {
+        let store = store().await;
+        store.register_owner("login-hash", &vault("alice")).await.unwrap();
+
+        assert!(matches!(
+            store.register_owner("other-hash", &vault("alice")).await,
+            Err(StoreError::Conflict)
+        ));
+        assert_eq!(store.get_login_hash("alice").await.unwrap().as_deref(), Some("login-hash"));
+        assert_eq!(store.get_vault("alice").await.unwrap().unwrap().kdf_salt, "c2FsdA==");
+        assert!(store.get_login_hash("bob").await.unwrap().is_none());
+        assert!(store.get_vault("bob").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn sessions_resolve_to_their_owner_until_they_expire() {
+        let store = store().await;
+        store.register_owner("login-hash", &vault("alice")).await.unwrap();
+
+        store.create_session("token-hash", "alice", 3600).await.unwrap();
+        assert_eq!(store.session_owner("token-hash").await.unwrap().as_deref(), Some("alice"));
+        assert!(store.session_owner("other-hash").await.unwrap().is_none());
+
+        store.create_session("expired-hash", "alice", 0).await.unwrap();
+        assert!(store.session_owner("expired-hash").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,365 @@
+// Router-level tests: every request goes through password_api::app over a
+// MemoryStore, so they need no database or network.
+use argon2::{
+    password_hash::{PasswordHasher, SaltString},
+    Argon2,
+};
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use password_api::store::{MemoryStore, RecordStore};
+use password_api::HashingPool;
+use password_common::{routes, AddEntry};
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const ACCOUNT_PASSWORD: &str = "Tq9#vLm2!xZr8pWe";
+
+fn server(store: Arc<MemoryStore>) -> Router {
+    password_api::app(store, HashingPool::new(2), Vec::new())
+}
+
+// Sends one request and returns the status with the parsed ApiResponse body
+async fn call(app: &Router, method: Method, uri: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn vault_header(owner: &str) -> Value {
+    json!({
+        "owner": owner,
+        "kdf_salt": "c2FsdHNhbHRzYWx0c2FsdA==",
+        "kdf_memory_kib": 19456,
+        "kdf_iterations": 2,
+        "kdf_parallelism": 1,
+        "key_check_ciphertext": "Y2lwaGVydGV4dA==",
+        "key_check_nonce": "bm9uY2U=",
+    })
+}
+
+fn login_secret(owner: &str) -> String {
+    format!("login-secret-of-{}", owner)
+}
+
+async fn register(app: &Router, owner: &str) -> StatusCode {
+    let body = json!({ "owner": owner, "password": login_secret(owner), "vault": vault_header(owner) });
+    call(app, Method::POST, "/register", None, Some(body)).await.0
+}
+
+// Registers the owner and returns a session token
+async fn sign_up(app: &Router, owner: &str) -> String {
+    assert_eq!(register(app, owner).await, StatusCode::CREATED);
+    let body = json!({ "owner": owner, "password": login_secret(owner) });
+    let (status, response) = call(app, Method::POST, "/login", None, Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+    response["data"]["token"].as_str().unwrap().to_string()
+}
+
+fn argon2_hash(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+fn new_entry(owner: &str, name: &str, hash: &str) -> AddEntry {
+    AddEntry {
+        owner: owner.to_string(),
+        name: name.to_string(),
+        username: "user".to_string(),
+        password: hash.to_string(),
+        ciphertext: "Y2lwaGVydGV4dA==".to_string(),
+        nonce: "bm9uY2U=".to_string(),
+    }
+}
+
+async fn add(app: &Router, token: &str, entry: &AddEntry) -> StatusCode {
+    let body = serde_json::to_value(entry).unwrap();
+    call(app, Method::POST, "/add", Some(token), Some(body)).await.0
+}
+
+// Ids of the owner's entries, oldest first
+async fn entry_ids(app: &Router, token: &str, owner: &str) -> Vec<u64> {
+    let (status, response) = call(app, Method::GET, &routes::entries(owner), Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    response["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["id"].as_u64().unwrap())
+        .collect()
+}
+
+async fn verify(app: &Router, token: &str, id: u64, password: &str) -> (StatusCode, Value) {
+    let body = json!({ "password": password });
+    call(app, Method::POST, &format!("/entries/id/{}/verify", id), Some(token), Some(body)).await
+}
+
+#[tokio::test]
+async fn records_routes_need_a_session() {
+    let app = server(Arc::default());
+    sign_up(&app, "alice").await;
+
+    assert_eq!(call(&app, Method::GET, "/entries/alice", None, None).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        call(&app, Method::GET, "/entries/alice", Some("not-a-token"), None).await.0,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn tokens_only_reach_their_own_owner() {
+    let app = server(Arc::default());
+    let alice = sign_up(&app, "alice").await;
+    let bob = sign_up(&app, "bob").await;
+
+    assert_eq!(add(&app, &alice, &new_entry("alice", "mail", &argon2_hash(ACCOUNT_PASSWORD))).await, StatusCode::CREATED);
+    let id = entry_ids(&app, &alice, "alice").await[0];
+
+    // Routes naming the owner refuse the other owner's token
+    assert_eq!(add(&app, &bob, &new_entry("alice", "planted", "x")).await, StatusCode::FORBIDDEN);
+    assert_eq!(call(&app, Method::GET, "/entries/alice", Some(&bob), None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(
+        call(&app, Method::DELETE, "/delete/alice/mail", Some(&bob), None).await.0,
+        StatusCode::FORBIDDEN
+    );
+
+    // Routes by id report another owner's record as missing
+    let by_id = format!("/entries/id/{}", id);
+    assert_eq!(call(&app, Method::GET, &by_id, Some(&bob), None).await.0, StatusCode::NOT_FOUND);
+    let rename = json!({ "name": "stolen" });
+    assert_eq!(call(&app, Method::PUT, &by_id, Some(&bob), Some(rename)).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(call(&app, Method::DELETE, &by_id, Some(&bob), None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(verify(&app, &bob, id, ACCOUNT_PASSWORD).await.0, StatusCode::NOT_FOUND);
+
+    // Nothing of alice's changed
+    let (status, response) = call(&app, Method::GET, &by_id, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["data"]["account_name"], "mail");
+    assert!(entry_ids(&app, &bob, "bob").await.is_empty());
+}
+
+#[tokio::test]
+async fn entries_are_read_updated_and_deleted_by_id() {
+    let app = server(Arc::default());
+    let alice = sign_up(&app, "alice").await;
+
+    // Two entries with the same name stay separate records
+    add(&app, &alice, &new_entry("alice", "mail", "hash-one")).await;
+    add(&app, &alice, &new_entry("alice", "mail", "hash-two")).await;
+    let ids = entry_ids(&app, &alice, "alice").await;
+    assert_eq!(ids.len(), 2);
+    let (first, second) = (ids[0], ids[1]);
+
+    let rename = json!({ "name": "work mail", "username": "alice@example.com" });
+    let (status, _) = call(&app, Method::PUT, &format!("/entries/id/{}", first), Some(&alice), Some(rename)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, response) = call(&app, Method::GET, &format!("/entries/id/{}", first), Some(&alice), None).await;
+    assert_eq!(response["data"]["account_name"], "work mail");
+    assert_eq!(response["data"]["account_username"], "alice@example.com");
+    let (_, response) = call(&app, Method::GET, &format!("/entries/id/{}", second), Some(&alice), None).await;
+    assert_eq!(response["data"]["account_name"], "mail");
+
+    // A new hash is only accepted together with a new ciphertext and nonce
+    let partial = json!({ "password": "hash-three" });
+    let (status, _) = call(&app, Method::PUT, &format!("/entries/id/{}", second), Some(&alice), Some(partial)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = call(&app, Method::DELETE, &format!("/entries/id/{}", first), Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(entry_ids(&app, &alice, "alice").await, vec![second]);
+
+    let (status, _) = call(&app, Method::DELETE, &format!("/entries/id/{}", first), Some(&alice), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn adding_answers_with_the_new_id_but_not_the_hash() {
+    let app = server(Arc::default());
+    let alice = sign_up(&app, "alice").await;
+    let body = serde_json::to_value(new_entry("alice", "mail", &argon2_hash(ACCOUNT_PASSWORD))).unwrap();
+
+    let (status, response) = call(&app, Method::POST, "/add", Some(&alice), Some(body)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let data = &response["data"];
+    assert_eq!(data["id"].as_u64(), Some(entry_ids(&app, &alice, "alice").await[0]));
+    assert_eq!(data["account_name"], "mail");
+    assert_eq!(data["account_password"], Value::Null);
+    assert!(data["created_at"].is_string());
+    assert_eq!(data["created_at"], data["updated_at"]);
+}
+
+#[tokio::test]
+async fn deleting_by_id_keeps_same_name_duplicates() {
+    let app = server(Arc::default());
+    let alice = sign_up(&app, "alice").await;
+
+    for _ in 0..3 {
+        add(&app, &alice, &new_entry("alice", "bank", "hash")).await;
+    }
+    let ids = entry_ids(&app, &alice, "alice").await;
+
+    call(&app, Method::DELETE, &format!("/entries/id/{}", ids[1]), Some(&alice), None).await;
+    assert_eq!(entry_ids(&app, &alice, "alice").await, vec![ids[0], ids[2]]);
+
+    // Deleting by name still removes all of them
+    let (status, _) = call(&app, Method::DELETE, "/delete/alice/bank", Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(entry_ids(&app, &alice, "alice").await.is_empty());
+}
+
+#[tokio::test]
+async fn owners_and_names_are_escaped_in_paths() {
+    let app = server(Arc::default());
+    let owner = "o'hara & co/east";
+    let token = sign_up(&app, owner).await;
+
+    let name = "mail/work?tab=1#top";
+    add(&app, &token, &new_entry(owner, name, "hash")).await;
+    add(&app, &token, &new_entry(owner, "mail", "hash")).await;
+    assert_eq!(entry_ids(&app, &token, owner).await.len(), 2);
+
+    let (status, _) = call(&app, Method::DELETE, &routes::delete_by_name(owner, name), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, response) = call(&app, Method::GET, &routes::entries(owner), Some(&token), None).await;
+    assert_eq!(response["data"][0]["account_name"], "mail");
+
+    let (status, response) = call(&app, Method::GET, &routes::vault(owner), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["data"]["owner"], owner);
+    let (status, _) = call(&app, Method::GET, &routes::key_check(owner), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn verify_reports_matches_and_mismatches() {
+    let app = server(Arc::default());
+    let alice = sign_up(&app, "alice").await;
+    add(&app, &alice, &new_entry("alice", "mail", &argon2_hash(ACCOUNT_PASSWORD))).await;
+    let id = entry_ids(&app, &alice, "alice").await[0];
+
+    let (status, response) = verify(&app, &alice, id, ACCOUNT_PASSWORD).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["data"]["matches"], true);
+    assert_eq!(response["data"]["hash_parameters"], "$argon2id$v=19$m=4096,t=3,p=1");
+
+    let (status, response) = verify(&app, &alice, id, "not the password").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["data"]["matches"], false);
+    assert_eq!(response["data"]["hash_parameters"], Value::Null);
+
+    assert_eq!(verify(&app, &alice, id + 100, ACCOUNT_PASSWORD).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn verify_rejects_missing_and_damaged_hashes() {
+    let app = server(Arc::default());
+    let alice = sign_up(&app, "alice").await;
+
+    let intact = argon2_hash(ACCOUNT_PASSWORD);
+    let without_output = intact.rsplit_once('$').unwrap().0.to_string();
+    for hash in ["", "not a hash", "$argon2id$v=19$m=4096,t=3,p=1", &without_output] {
+        add(&app, &alice, &new_entry("alice", "damaged", hash)).await;
+    }
+
+    for id in entry_ids(&app, &alice, "alice").await {
+        let (status, _) = verify(&app, &alice, id, ACCOUNT_PASSWORD).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "entry {}", id);
+    }
+}
+
+#[tokio::test]
+async fn hashes_are_left_out_unless_asked_for() {
+    let app = server(Arc::default());
+    let alice = sign_up(&app, "alice").await;
+    let hash = argon2_hash(ACCOUNT_PASSWORD);
+    add(&app, &alice, &new_entry("alice", "mail", &hash)).await;
+    let id = entry_ids(&app, &alice, "alice").await[0];
+
+    let (_, response) = call(&app, Method::GET, "/entries/alice", Some(&alice), None).await;
+    assert_eq!(response["data"][0]["account_password"], Value::Null);
+    assert_eq!(response["data"][0]["account_ciphertext"], "Y2lwaGVydGV4dA==");
+    let (_, response) = call(&app, Method::GET, &format!("/entries/id/{}", id), Some(&alice), None).await;
+    assert_eq!(response["data"]["account_password"], Value::Null);
+
+    let (_, response) = call(&app, Method::GET, "/entries/alice?include_hash=true", Some(&alice), None).await;
+    assert_eq!(response["data"][0]["account_password"], hash.as_str());
+    let uri = format!("/entries/id/{}?include_hash=true", id);
+    let (_, response) = call(&app, Method::GET, &uri, Some(&alice), None).await;
+    assert_eq!(response["data"]["account_password"], hash.as_str());
+}
+
+#[tokio::test]
+async fn vault_lookups_do_not_reveal_owners_or_the_key_check() {
+    let app = server(Arc::default());
+    let alice = sign_up(&app, "alice").await;
+    let bob = sign_up(&app, "bob").await;
+
+    let (status, known) = call(&app, Method::GET, "/vault/alice", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(known["data"]["kdf_salt"], "c2FsdHNhbHRzYWx0c2FsdA==");
+    assert_eq!(known["data"].get("key_check_ciphertext"), None);
+
+    // Made-up but stable answers for owners that do not exist
+    let (status, unknown) = call(&app, Method::GET, "/vault/nobody", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, again) = call(&app, Method::GET, "/vault/nobody", None, None).await;
+    assert_eq!(unknown, again);
+    let (_, other) = call(&app, Method::GET, "/vault/somebody", None, None).await;
+    assert_ne!(unknown["data"]["kdf_salt"], other["data"]["kdf_salt"]);
+
+    let key_check = "/vault/alice/key-check";
+    assert_eq!(call(&app, Method::GET, key_check, None, None).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(call(&app, Method::GET, key_check, Some(&bob), None).await.0, StatusCode::FORBIDDEN);
+    let (status, response) = call(&app, Method::GET, key_check, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["data"]["ciphertext"], "Y2lwaGVydGV4dA==");
+
+    // Unknown owners and wrong passwords fail the same way
+    let wrong = json!({ "owner": "alice", "password": "wrong" });
+    let (status, wrong) = call(&app, Method::POST, "/login", None, Some(wrong)).await;
+    let missing = json!({ "owner": "nobody", "password": "wrong" });
+    let (missing_status, missing) = call(&app, Method::POST, "/login", None, Some(missing)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!((missing_status, missing), (status, wrong));
+}
+
+#[tokio::test]
+async fn owners_register_once() {
+    let app = server(Arc::default());
+    sign_up(&app, "alice").await;
+    assert_eq!(register(&app, "alice").await, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn records_from_before_accounts_need_an_operator_to_be_claimed() {
+    let store = Arc::new(MemoryStore::default());
+    store.add_entry(&new_entry("carol", "old", "hash")).await.unwrap();
+
+    let app = server(store.clone());
+    assert_eq!(register(&app, "carol").await, StatusCode::CONFLICT);
+
+    let app = password_api::app(store, HashingPool::new(2), vec!["carol".to_string()]);
+    let carol = sign_up(&app, "carol").await;
+    assert_eq!(entry_ids(&app, &carol, "carol").await.len(), 1);
+}
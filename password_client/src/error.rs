@@ -0,0 +1,77 @@
+use password_common::ApiError;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ClientError {
+    // The request could not be sent or the response could not be read
+    Http(reqwest::Error),
+    // The server answered with an error status
+    Api(ApiError),
+    // The body was not the ApiResponse JSON the server normally sends
+    InvalidResponse { status: u16, body: String },
+    // A successful response came without the data it should carry
+    MissingData,
+    // The call needs a bearer token and the client has not logged in
+    NotLoggedIn,
+    // The TLS trust settings could not be used
+    Tls(String),
+}
+
+impl ClientError {
+    // The error the server reported, if the call got that far
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            ClientError::Api(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self, ClientError::Api(ApiError::Unauthorized(_)))
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ClientError::Api(ApiError::NotFound(_)))
+    }
+
+    pub fn is_unprocessable(&self) -> bool {
+        matches!(self, ClientError::Api(ApiError::Unprocessable(_)))
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Http(e) => write!(f, "Failed to contact server: {}", e),
+            ClientError::Api(e) => write!(f, "{}", e),
+            ClientError::InvalidResponse { status, body } => {
+                write!(f, "Unexpected server response [{}]: {}", status, body)
+            }
+            ClientError::MissingData => write!(f, "Server response is missing its data"),
+            ClientError::NotLoggedIn => write!(f, "Not logged in"),
+            ClientError::Tls(reason) => write!(f, "TLS setup failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Http(e) => Some(e),
+            ClientError::Api(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
+
+impl From<ApiError> for ClientError {
+    fn from(e: ApiError) -> Self {
+        ClientError::Api(e)
+    }
+}
@@ -0,0 +1,238 @@
+// Async client for the password_api HTTP API. Every call returns the data of
+// the ApiResponse envelope, or a ClientError describing why there is none.
+use password_common::{
+    routes, AddEntry, ApiResponse, Credentials, Entry, GeneratedPassword, KeyCheck, LoginResponse, PasswordPolicy,
+    RegisterRequest, UpdateEntry, VaultParams, VerifyRequest, VerifyResult,
+};
+use reqwest::{header::CONTENT_TYPE, Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+mod error;
+mod secret;
+mod tls;
+pub use error::ClientError;
+pub use secret::SecretString;
+pub use tls::{parse_fingerprint, TlsTrust};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Settings for the underlying HTTP client
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    // Bounds a whole request
+    pub timeout: Duration,
+    // Bounds only establishing the connection
+    pub connect_timeout: Duration,
+    pub tls: TlsTrust,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        ClientOptions {
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            tls: TlsTrust::default(),
+        }
+    }
+}
+
+pub struct PasswordApiClient {
+    http: Client,
+    base_url: String,
+    // Bearer token of the current session, set by login
+    token: Option<String>,
+}
+
+impl PasswordApiClient {
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        Self::with_options(base_url, &ClientOptions::default())
+    }
+
+    pub fn with_timeouts(base_url: &str, timeout: Duration, connect_timeout: Duration) -> Result<Self, ClientError> {
+        let options = ClientOptions {
+            timeout,
+            connect_timeout,
+            ..ClientOptions::default()
+        };
+        Self::with_options(base_url, &options)
+    }
+
+    pub fn with_options(base_url: &str, options: &ClientOptions) -> Result<Self, ClientError> {
+        let builder = Client::builder()
+            .timeout(options.timeout)
+            .connect_timeout(options.connect_timeout);
+        let http = tls::apply(builder, &options.tls)?.build()?;
+
+        Ok(PasswordApiClient {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: None,
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    // Reuse a token obtained elsewhere instead of logging in
+    pub fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    // ---------------- Owners and sessions ----------------
+
+    // The key derivation inputs of the owner's vault. The server answers for
+    // every owner, so this does not tell whether the owner has an account;
+    // a failed login does.
+    pub async fn get_vault(&self, owner: &str) -> Result<VaultParams, ClientError> {
+        let request = self.http.get(self.url(&routes::vault(owner)));
+        self.send(request).await?.ok_or(ClientError::MissingData)
+    }
+
+    // Needs a session of this owner
+    pub async fn get_key_check(&self, owner: &str) -> Result<KeyCheck, ClientError> {
+        let request = self.authorized(self.http.get(self.url(&routes::key_check(owner))))?;
+        self.send(request).await?.ok_or(ClientError::MissingData)
+    }
+
+    // The request body is wiped once the call is done
+    pub async fn register(&self, request: &RegisterRequest<&str>) -> Result<(), ClientError> {
+        // The vault header comes after the password, so the buffer must have
+        // room for it as well
+        let header_len = serde_json::to_vec(&request.vault).map_or(0, |header| header.len());
+        let body = secret::json_body(request, request.password.len() + header_len);
+        let request = self
+            .http
+            .post(self.url(routes::REGISTER))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+
+        let result = self.send::<()>(request).await;
+        secret::wipe(body);
+        result?;
+        Ok(())
+    }
+
+    // On success the token is kept and sent with every later call. The
+    // request body is wiped once the call is done.
+    pub async fn login(&mut self, credentials: &Credentials<&str>) -> Result<LoginResponse, ClientError> {
+        let body = secret::json_body(credentials, credentials.password.len());
+        let request = self
+            .http
+            .post(self.url(routes::LOGIN))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+
+        let result = self.send(request).await;
+        secret::wipe(body);
+        let login: LoginResponse = result?.ok_or(ClientError::MissingData)?;
+
+        self.token = Some(login.token.clone());
+        Ok(login)
+    }
+
+    // ---------------- Entries ----------------
+
+    pub async fn list_entries(&self, owner: &str) -> Result<Vec<Entry>, ClientError> {
+        let request = self.authorized(self.http.get(self.url(&routes::entries(owner))))?;
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    // The same entries with their password hashes, for tools that need them;
+    // checking a password should go through verify_entry instead
+    pub async fn list_entries_with_hashes(&self, owner: &str) -> Result<Vec<Entry>, ClientError> {
+        let url = format!("{}?include_hash=true", self.url(&routes::entries(owner)));
+        let request = self.authorized(self.http.get(url))?;
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn get_entry(&self, id: u64) -> Result<Entry, ClientError> {
+        let request = self.authorized(self.http.get(self.url(&routes::entry_by_id(id))))?;
+        self.send(request).await?.ok_or(ClientError::MissingData)
+    }
+
+    // The new entry as stored, without its password hash
+    pub async fn add_entry(&self, entry: &AddEntry) -> Result<Entry, ClientError> {
+        let request = self.authorized(self.http.post(self.url(routes::ADD)))?.json(entry);
+        self.send(request).await?.ok_or(ClientError::MissingData)
+    }
+
+    // Asks the server whether the password matches the entry's stored hash.
+    // A hash that is missing or damaged comes back as an unprocessable error.
+    // The request body is wiped once the call is done.
+    pub async fn verify_entry(&self, id: u64, password: &SecretString) -> Result<VerifyResult, ClientError> {
+        let body = VerifyRequest {
+            password: password.expose(),
+        };
+        let body = secret::json_body(&body, password.expose().len());
+        let request = self
+            .authorized(self.http.post(self.url(&routes::verify_entry(id))))?
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+
+        let result = self.send(request).await;
+        secret::wipe(body);
+        result?.ok_or(ClientError::MissingData)
+    }
+
+    pub async fn update_entry(&self, id: u64, update: &UpdateEntry) -> Result<(), ClientError> {
+        let request = self
+            .authorized(self.http.put(self.url(&routes::entry_by_id(id))))?
+            .json(update);
+        self.send::<()>(request).await?;
+        Ok(())
+    }
+
+    pub async fn delete_entry(&self, id: u64) -> Result<(), ClientError> {
+        let request = self.authorized(self.http.delete(self.url(&routes::entry_by_id(id))))?;
+        self.send::<()>(request).await?;
+        Ok(())
+    }
+
+    // Removes every entry of the owner with this name
+    pub async fn delete_entries_by_name(&self, owner: &str, name: &str) -> Result<(), ClientError> {
+        let request = self.authorized(self.http.delete(self.url(&routes::delete_by_name(owner, name))))?;
+        self.send::<()>(request).await?;
+        Ok(())
+    }
+
+    // ---------------- Generator ----------------
+
+    // A random password made by the server; password_common::generate_password
+    // does the same without a round trip
+    pub async fn generate_password(&self, policy: &PasswordPolicy) -> Result<String, ClientError> {
+        let request = self.http.post(self.url(routes::GENERATE)).json(policy);
+        let generated: GeneratedPassword = self.send(request).await?.ok_or(ClientError::MissingData)?;
+        Ok(generated.password)
+    }
+
+    // ---------------- Helpers ----------------
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn authorized(&self, request: RequestBuilder) -> Result<RequestBuilder, ClientError> {
+        match &self.token {
+            Some(token) => Ok(request.bearer_auth(token)),
+            None => Err(ClientError::NotLoggedIn),
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<Option<T>, ClientError> {
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let body = response.text().await?;
+
+        match serde_json::from_str::<ApiResponse<T>>(&body) {
+            Ok(api_resp) => Ok(api_resp.into_result()?),
+            Err(_) => Err(ClientError::InvalidResponse { status, body }),
+        }
+    }
+}
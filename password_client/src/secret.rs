@@ -0,0 +1,116 @@
+use bytes::Bytes;
+use serde::Serialize;
+use std::fmt;
+use zeroize::Zeroize;
+
+// A password or other secret held in memory. The buffer is wiped when it is
+// dropped, it has no Display and its Debug output is redacted, and with the
+// mlock feature the buffer is locked so it is never written to swap.
+pub struct SecretString {
+    inner: String,
+}
+
+impl SecretString {
+    // Takes over the buffer without copying it
+    pub fn new(inner: String) -> Self {
+        memory::lock(&inner);
+        SecretString { inner }
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.inner
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    // For filling in place. Growing past the capacity would leave an
+    // unwiped copy behind, so reserve enough up front.
+    pub fn buffer(&mut self) -> &mut String {
+        &mut self.inner
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(inner: String) -> Self {
+        SecretString::new(inner)
+    }
+}
+
+// The copy is a secret of its own, locked and wiped separately
+impl Clone for SecretString {
+    fn clone(&self) -> Self {
+        SecretString::new(self.inner.clone())
+    }
+}
+
+// Compares every byte so the time taken does not reveal where they differ
+impl PartialEq for SecretString {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.inner.as_bytes(), other.inner.as_bytes());
+        a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+    }
+}
+
+impl Eq for SecretString {}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        // Zeroize covers the whole capacity, not just the current length
+        self.inner.zeroize();
+        memory::unlock(&self.inner);
+    }
+}
+
+// A JSON request body carrying a secret. The buffer is sized up front so
+// serializing never reallocates and leaves a stray copy behind. `secret_len`
+// is the length of the secrets it holds.
+pub(crate) fn json_body<T: Serialize>(value: &T, secret_len: usize) -> Bytes {
+    // Each byte escapes to at most six, plus room for the field names
+    let mut buf = Vec::with_capacity(secret_len * 6 + 256);
+    serde_json::to_writer(&mut buf, value).expect("serializing to memory does not fail");
+    Bytes::from(buf)
+}
+
+// Wipes a body made by json_body once the request no longer holds it. Copies
+// made further down, in hyper's or the TLS layer's buffers, are out of reach.
+pub(crate) fn wipe(body: Bytes) {
+    if let Ok(mut buf) = body.try_into_mut() {
+        buf.as_mut().zeroize();
+    }
+}
+
+// Locking is best effort: it fails quietly past RLIMIT_MEMLOCK, and as the
+// kernel locks whole pages, unlocking one secret may unlock a neighbour
+// sharing its page.
+#[cfg(all(feature = "mlock", unix))]
+mod memory {
+    pub fn lock(buf: &String) {
+        if buf.capacity() > 0 {
+            unsafe {
+                libc::mlock(buf.as_ptr().cast(), buf.capacity());
+            }
+        }
+    }
+
+    pub fn unlock(buf: &String) {
+        if buf.capacity() > 0 {
+            unsafe {
+                libc::munlock(buf.as_ptr().cast(), buf.capacity());
+            }
+        }
+    }
+}
+
+#[cfg(not(all(feature = "mlock", unix)))]
+mod memory {
+    pub fn lock(_buf: &String) {}
+    pub fn unlock(_buf: &String) {}
+}
@@ -0,0 +1,89 @@
+use reqwest::{Certificate, ClientBuilder};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{ClientConfig, ServerName};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::ClientError;
+
+// Which server certificates the client accepts over https
+#[derive(Debug, Clone, Default)]
+pub enum TlsTrust {
+    // Certificates issued by the bundled web PKI roots
+    #[default]
+    WebPki,
+    // Only certificates issued by these PEM encoded CA certificates
+    CaCertificates(Vec<u8>),
+    // Only the certificate with this SHA-256 fingerprint, whoever issued it.
+    // Suits self-signed server certificates.
+    Fingerprint([u8; 32]),
+}
+
+// Accepts 64 hex digits, optionally separated by colons as printed by
+// "openssl x509 -fingerprint -sha256"
+pub fn parse_fingerprint(text: &str) -> Option<[u8; 32]> {
+    let hex: String = text.chars().filter(|c| *c != ':').collect();
+    if hex.len() != 64 {
+        return None;
+    }
+
+    let mut fingerprint = [0u8; 32];
+    for (i, byte) in fingerprint.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(fingerprint)
+}
+
+pub(crate) fn apply(builder: ClientBuilder, trust: &TlsTrust) -> Result<ClientBuilder, ClientError> {
+    match trust {
+        TlsTrust::WebPki => Ok(builder.use_rustls_tls()),
+        TlsTrust::CaCertificates(pem) => {
+            let ders = rustls_pemfile::certs(&mut pem.as_slice())
+                .map_err(|e| ClientError::Tls(format!("invalid CA certificate file: {}", e)))?;
+            if ders.is_empty() {
+                return Err(ClientError::Tls("CA certificate file holds no certificates".to_string()));
+            }
+
+            let mut builder = builder.use_rustls_tls().tls_built_in_root_certs(false);
+            for der in ders {
+                let cert = Certificate::from_der(&der)
+                    .map_err(|e| ClientError::Tls(format!("invalid CA certificate: {}", e)))?;
+                builder = builder.add_root_certificate(cert);
+            }
+            Ok(builder)
+        }
+        TlsTrust::Fingerprint(fingerprint) => {
+            let config = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_custom_certificate_verifier(Arc::new(FingerprintVerifier(*fingerprint)))
+                .with_no_client_auth();
+            Ok(builder.use_preconfigured_tls(config))
+        }
+    }
+}
+
+// Trusts exactly one end-entity certificate. The handshake signature is
+// still checked by rustls, so the server must hold the matching key.
+struct FingerprintVerifier([u8; 32]);
+
+impl ServerCertVerifier for FingerprintVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let actual: [u8; 32] = Sha256::digest(&end_entity.0).into();
+        if actual == self.0 {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "server certificate does not match the pinned fingerprint".to_string(),
+            ))
+        }
+    }
+}
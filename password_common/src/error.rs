@@ -0,0 +1,64 @@
+use std::fmt;
+
+// A failed API call, classified by the http_code of the response so both
+// sides agree on what each status means.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    BadRequest(String),
+    // Missing, invalid or expired bearer token, or wrong login
+    Unauthorized(String),
+    // The token belongs to a different owner
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    // The request was understood but the record cannot serve it, e.g. its
+    // stored password hash is missing or damaged
+    Unprocessable(String),
+    Server { http_code: u16, message: String },
+}
+
+impl ApiError {
+    pub fn from_http_code(http_code: u16, message: String) -> Self {
+        match http_code {
+            400 => ApiError::BadRequest(message),
+            401 => ApiError::Unauthorized(message),
+            403 => ApiError::Forbidden(message),
+            404 => ApiError::NotFound(message),
+            409 => ApiError::Conflict(message),
+            422 => ApiError::Unprocessable(message),
+            _ => ApiError::Server { http_code, message },
+        }
+    }
+
+    pub fn http_code(&self) -> u16 {
+        match self {
+            ApiError::BadRequest(_) => 400,
+            ApiError::Unauthorized(_) => 401,
+            ApiError::Forbidden(_) => 403,
+            ApiError::NotFound(_) => 404,
+            ApiError::Conflict(_) => 409,
+            ApiError::Unprocessable(_) => 422,
+            ApiError::Server { http_code, .. } => *http_code,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::BadRequest(m)
+            | ApiError::Unauthorized(m)
+            | ApiError::Forbidden(m)
+            | ApiError::NotFound(m)
+            | ApiError::Conflict(m)
+            | ApiError::Unprocessable(m) => m,
+            ApiError::Server { message, .. } => message,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Server [{}]: {}", self.http_code(), self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
@@ -0,0 +1,214 @@
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const DEFAULT_LENGTH: usize = 20;
+pub const MIN_LENGTH: usize = 4;
+pub const MAX_LENGTH: usize = 256;
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!#$%&()*+,-./:;<=>?@[]^_{}~";
+// Characters easily mistaken for one another when read or typed
+const AMBIGUOUS: &str = "Il1O0o";
+
+// What a generated password is made of. Fields left out of a request body
+// take their default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    pub exclude_ambiguous: bool,
+    // At least one character from every enabled class
+    pub require_each_class: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            length: DEFAULT_LENGTH,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+            require_each_class: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    NoCharacterClasses,
+    TooShort { min: usize },
+    TooLong { max: usize },
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::NoCharacterClasses => write!(f, "at least one character class must be enabled"),
+            PolicyError::TooShort { min } => write!(f, "length must be at least {}", min),
+            PolicyError::TooLong { max } => write!(f, "length must be at most {}", max),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+impl PasswordPolicy {
+    // The enabled character classes, without ambiguous characters if excluded
+    fn classes(&self) -> Vec<Vec<char>> {
+        [
+            (self.lowercase, LOWERCASE),
+            (self.uppercase, UPPERCASE),
+            (self.digits, DIGITS),
+            (self.symbols, SYMBOLS),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, chars)| {
+            chars
+                .chars()
+                .filter(|c| !(self.exclude_ambiguous && AMBIGUOUS.contains(*c)))
+                .collect()
+        })
+        .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), PolicyError> {
+        let classes = self.classes();
+        if classes.is_empty() {
+            return Err(PolicyError::NoCharacterClasses);
+        }
+
+        let min = if self.require_each_class {
+            MIN_LENGTH.max(classes.len())
+        } else {
+            MIN_LENGTH
+        };
+        if self.length < min {
+            return Err(PolicyError::TooShort { min });
+        }
+        if self.length > MAX_LENGTH {
+            return Err(PolicyError::TooLong { max: MAX_LENGTH });
+        }
+
+        Ok(())
+    }
+}
+
+// A random password drawn from the operating system's CSPRNG
+pub fn generate_password(policy: &PasswordPolicy) -> Result<String, PolicyError> {
+    policy.validate()?;
+
+    let classes = policy.classes();
+    let all: Vec<char> = classes.iter().flatten().copied().collect();
+    let mut rng = OsRng;
+    let mut password: Vec<char> = Vec::with_capacity(policy.length);
+
+    // One from each class first, then shuffle so their positions are random too
+    if policy.require_each_class {
+        for class in &classes {
+            password.extend(class.choose(&mut rng));
+        }
+    }
+    while password.len() < policy.length {
+        password.extend(all.choose(&mut rng));
+    }
+    password.shuffle(&mut rng);
+
+    Ok(password.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_enabled_class_is_used() {
+        // At the minimum length each class gets exactly one character
+        let policy = PasswordPolicy {
+            length: MIN_LENGTH,
+            ..PasswordPolicy::default()
+        };
+        for _ in 0..200 {
+            let password = generate_password(&policy).unwrap();
+            assert_eq!(password.chars().count(), MIN_LENGTH);
+            for class in [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS] {
+                assert!(password.chars().any(|c| class.contains(c)), "{} lacks one of {}", password, class);
+            }
+        }
+
+        let policy = PasswordPolicy {
+            length: 8,
+            symbols: false,
+            digits: false,
+            ..PasswordPolicy::default()
+        };
+        for _ in 0..200 {
+            let password = generate_password(&policy).unwrap();
+            assert!(password.chars().all(|c| c.is_ascii_alphabetic()));
+            assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+        }
+    }
+
+    #[test]
+    fn ambiguous_characters_can_be_left_out() {
+        let policy = PasswordPolicy {
+            length: MAX_LENGTH,
+            exclude_ambiguous: true,
+            ..PasswordPolicy::default()
+        };
+        for _ in 0..20 {
+            let password = generate_password(&policy).unwrap();
+            assert!(!password.chars().any(|c| AMBIGUOUS.contains(c)), "{}", password);
+        }
+    }
+
+    #[test]
+    fn length_must_fit_the_classes_and_the_limit() {
+        let too_short = PasswordPolicy {
+            length: 3,
+            ..PasswordPolicy::default()
+        };
+        assert_eq!(generate_password(&too_short), Err(PolicyError::TooShort { min: MIN_LENGTH }));
+        assert_eq!(
+            generate_password(&PasswordPolicy {
+                require_each_class: false,
+                ..too_short
+            }),
+            Err(PolicyError::TooShort { min: MIN_LENGTH })
+        );
+
+        let too_long = PasswordPolicy {
+            length: MAX_LENGTH + 1,
+            ..PasswordPolicy::default()
+        };
+        assert_eq!(generate_password(&too_long), Err(PolicyError::TooLong { max: MAX_LENGTH }));
+        let longest = generate_password(&PasswordPolicy {
+            length: MAX_LENGTH,
+            ..PasswordPolicy::default()
+        });
+        assert_eq!(longest.map(|p| p.chars().count()), Ok(MAX_LENGTH));
+    }
+
+    #[test]
+    fn some_class_must_be_enabled() {
+        let policy = PasswordPolicy {
+            lowercase: false,
+            uppercase: false,
+            digits: false,
+            symbols: false,
+            ..PasswordPolicy::default()
+        };
+        assert_eq!(generate_password(&policy), Err(PolicyError::NoCharacterClasses));
+    }
+}
@@ -0,0 +1,166 @@
+// Types shared by password_api and its clients. Everything here is part of
+// the JSON contract, so changing a field is a compile error on both sides.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub mod error;
+pub mod generator;
+pub mod passphrase;
+pub mod routes;
+
+pub use error::ApiError;
+pub use generator::{generate_password, PasswordPolicy, PolicyError};
+pub use passphrase::{generate_passphrase, Passphrase, PassphraseError, PassphrasePolicy, Wordlist};
+
+// Envelope for every response body
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    pub message: String,
+    pub http_code: u16,
+    pub data: Option<T>,
+}
+
+impl<T> ApiResponse<T> {
+    // The data of a 2xx response, or the error it describes
+    pub fn into_result(self) -> Result<Option<T>, ApiError> {
+        if (200..300).contains(&self.http_code) {
+            Ok(self.data)
+        } else {
+            Err(ApiError::from_http_code(self.http_code, self.message))
+        }
+    }
+}
+
+// The password is the Argon2 hash; ciphertext and nonce hold the password
+// encrypted under the owner's vault key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddEntry {
+    pub owner: String,
+    pub name: String,
+    pub username: String,
+    pub password: String,
+    pub ciphertext: String,
+    pub nonce: String,
+}
+
+// account_password is only filled in when asked for with ?include_hash=true;
+// passwords are checked with POST /entries/id/:id/verify instead
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: u64,
+    pub account_owner: Option<String>,
+    pub account_name: Option<String>,
+    pub account_username: Option<String>,
+    pub account_password: Option<String>,
+    pub account_ciphertext: Option<String>,
+    pub account_nonce: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Fields left out are kept as they are. The password hash, ciphertext and
+// nonce describe the same secret, so they must be changed together.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateEntry {
+    pub name: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub ciphertext: Option<String>,
+    pub nonce: Option<String>,
+}
+
+// Body of POST /entries/id/:id/verify. Clients can send a borrowed
+// password so it is not copied into a buffer they cannot wipe.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyRequest<S = String> {
+    pub password: S,
+}
+
+// hash_parameters is the stored hash without its salt and output, e.g.
+// "$argon2id$v=19$m=4096,t=3,p=1", sent only on a match so the client can
+// tell whether the hash should be upgraded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyResult {
+    pub matches: bool,
+    pub hash_parameters: Option<String>,
+}
+
+// Argon2id costs new vaults are created with unless the client overrides
+// them. The server answers with the same costs for owners it does not know.
+pub const DEFAULT_KDF_MEMORY_KIB: u32 = 19 * 1024;
+pub const DEFAULT_KDF_ITERATIONS: u32 = 2;
+pub const DEFAULT_KDF_PARALLELISM: u32 = 1;
+
+// Per-owner vault data as registered and stored: the key derivation inputs
+// and a known plaintext encrypted under the vault key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultHeader {
+    pub owner: String,
+    pub kdf_salt: String,
+    pub kdf_memory_kib: u32,
+    pub kdf_iterations: u32,
+    pub kdf_parallelism: u32,
+    pub key_check_ciphertext: String,
+    pub key_check_nonce: String,
+}
+
+// What anyone may learn about a vault before logging in: enough to derive
+// the key, nothing to test a guessed master password against. Owners without
+// an account get made-up values, so the answer does not reveal who exists.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultParams {
+    pub owner: String,
+    pub kdf_salt: String,
+    pub kdf_memory_kib: u32,
+    pub kdf_iterations: u32,
+    pub kdf_parallelism: u32,
+}
+
+impl From<&VaultHeader> for VaultParams {
+    fn from(header: &VaultHeader) -> Self {
+        VaultParams {
+            owner: header.owner.clone(),
+            kdf_salt: header.kdf_salt.clone(),
+            kdf_memory_kib: header.kdf_memory_kib,
+            kdf_iterations: header.kdf_iterations,
+            kdf_parallelism: header.kdf_parallelism,
+        }
+    }
+}
+
+// The vault's encrypted known plaintext, served only to the owner's own
+// session so the client can confirm the key it unlocked with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyCheck {
+    pub ciphertext: String,
+    pub nonce: String,
+}
+
+// Body of POST /login. Clients can send a borrowed password so it is not
+// copied into a buffer they cannot wipe.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Credentials<S = String> {
+    pub owner: String,
+    pub password: S,
+}
+
+// A new owner account is created together with its vault header, and the
+// password may be borrowed as in Credentials
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterRequest<S = String> {
+    pub owner: String,
+    pub password: S,
+    pub vault: VaultHeader,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginResponse {
+    pub token: String,
+    pub expires_in: u64,
+}
+
+// Response to a password generation request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeneratedPassword {
+    pub password: String,
+}
@@ -0,0 +1,290 @@
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const DEFAULT_WORDS: usize = 6;
+pub const MIN_WORDS: usize = 3;
+pub const MAX_WORDS: usize = 20;
+pub const DEFAULT_SEPARATOR: &str = "-";
+
+// The EFF long wordlist (https://www.eff.org/dice), as published with its
+// dice rolls: 7776 words, so about 12.9 bits per word
+const BUNDLED_WORDLIST: &str = include_str!("../wordlists/eff_large_wordlist.txt");
+
+// Words a passphrase is drawn from, without duplicates
+pub struct Wordlist {
+    words: Vec<String>,
+}
+
+impl Wordlist {
+    pub fn bundled() -> Self {
+        Wordlist::parse(BUNDLED_WORDLIST).expect("bundled wordlist is valid")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Wordlist::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // One word per line, either alone or after a dice roll as in the EFF and
+    // original diceware lists ("11111\tabacus"). Blank lines are skipped.
+    pub fn parse(text: &str) -> Result<Self, PassphraseError> {
+        let mut words = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let word = match fields.as_slice() {
+                [] => continue,
+                [word] => *word,
+                [roll, word] if roll.chars().all(|c| c.is_ascii_digit()) => *word,
+                _ => {
+                    return Err(PassphraseError::InvalidWordlist(format!(
+                        "line {} is not a word or a dice roll and a word",
+                        number + 1
+                    )))
+                }
+            };
+
+            // A repeated word would make the entropy figure a lie
+            if !seen.insert(word.to_string()) {
+                return Err(PassphraseError::InvalidWordlist(format!(
+                    "'{}' appears more than once",
+                    word
+                )));
+            }
+            words.push(word.to_string());
+        }
+
+        if words.len() < 2 {
+            return Err(PassphraseError::InvalidWordlist(
+                "it needs at least two words".to_string(),
+            ));
+        }
+
+        Ok(Wordlist { words })
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn bits_per_word(&self) -> f64 {
+        (self.words.len() as f64).log2()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capitalization {
+    // all lowercase
+    #[default]
+    None,
+    // First letter of every word
+    First,
+    // First letter of each word with even odds, one extra bit per word
+    Random,
+}
+
+impl FromStr for Capitalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Capitalization::None),
+            "first" => Ok(Capitalization::First),
+            "random" => Ok(Capitalization::Random),
+            _ => Err(format!("'{}' is not one of none, first, random", s)),
+        }
+    }
+}
+
+// How a passphrase is put together. Fields left out take their default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PassphrasePolicy {
+    pub words: usize,
+    pub separator: String,
+    pub capitalization: Capitalization,
+    // Append a random digit to one randomly chosen word
+    pub add_digit: bool,
+}
+
+impl Default for PassphrasePolicy {
+    fn default() -> Self {
+        PassphrasePolicy {
+            words: DEFAULT_WORDS,
+            separator: DEFAULT_SEPARATOR.to_string(),
+            capitalization: Capitalization::None,
+            add_digit: false,
+        }
+    }
+}
+
+impl PassphrasePolicy {
+    pub fn validate(&self) -> Result<(), PassphraseError> {
+        if self.words < MIN_WORDS {
+            return Err(PassphraseError::TooFewWords { min: MIN_WORDS });
+        }
+        if self.words > MAX_WORDS {
+            return Err(PassphraseError::TooManyWords { max: MAX_WORDS });
+        }
+        Ok(())
+    }
+
+    // Bits an attacker who knows the policy and the wordlist has to guess
+    pub fn entropy_bits(&self, wordlist: &Wordlist) -> f64 {
+        let mut bits = self.words as f64 * wordlist.bits_per_word();
+        if self.capitalization == Capitalization::Random {
+            bits += self.words as f64;
+        }
+        if self.add_digit {
+            bits += (10.0 * self.words as f64).log2();
+        }
+        bits
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassphraseError {
+    TooFewWords { min: usize },
+    TooManyWords { max: usize },
+    InvalidWordlist(String),
+}
+
+impl fmt::Display for PassphraseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassphraseError::TooFewWords { min } => write!(f, "a passphrase needs at least {} words", min),
+            PassphraseError::TooManyWords { max } => write!(f, "a passphrase can have at most {} words", max),
+            PassphraseError::InvalidWordlist(reason) => write!(f, "invalid wordlist: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PassphraseError {}
+
+pub struct Passphrase {
+    pub passphrase: String,
+    pub entropy_bits: f64,
+}
+
+// Words picked independently from the operating system's CSPRNG
+pub fn generate_passphrase(policy: &PassphrasePolicy, wordlist: &Wordlist) -> Result<Passphrase, PassphraseError> {
+    policy.validate()?;
+
+    let mut rng = OsRng;
+    let mut words: Vec<String> = Vec::with_capacity(policy.words);
+    for _ in 0..policy.words {
+        let word = wordlist.words.choose(&mut rng).expect("wordlist is not empty");
+        let capitalize = match policy.capitalization {
+            Capitalization::None => false,
+            Capitalization::First => true,
+            Capitalization::Random => rng.gen_bool(0.5),
+        };
+        words.push(if capitalize { capitalized(word) } else { word.clone() });
+    }
+
+    if policy.add_digit {
+        let index = rng.gen_range(0..words.len());
+        let digit = char::from(b'0' + rng.gen_range(0..10u8));
+        words[index].push(digit);
+    }
+
+    Ok(Passphrase {
+        passphrase: words.join(&policy.separator),
+        entropy_bits: policy.entropy_bits(wordlist),
+    })
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_wordlist_is_the_eff_long_list() {
+        let wordlist = Wordlist::bundled();
+        assert_eq!(wordlist.len(), 7776);
+        assert_eq!(wordlist.words().first().map(String::as_str), Some("abacus"));
+        assert_eq!(wordlist.words().last().map(String::as_str), Some("zoom"));
+        assert!((wordlist.bits_per_word() - 12.925).abs() < 0.001);
+    }
+
+    #[test]
+    fn parse_accepts_plain_words_and_dice_rolls() {
+        let wordlist = Wordlist::parse("11111\tabacus\n\n11112 abdomen\nabdominal\n").unwrap();
+        assert_eq!(wordlist.words(), ["abacus", "abdomen", "abdominal"]);
+
+        let err = Wordlist::parse("abacus\n11112 abdomen extra\n").err().expect("wordlist is rejected");
+        assert!(matches!(err, PassphraseError::InvalidWordlist(reason) if reason.contains("line 2")));
+        assert!(Wordlist::parse("abacus\nfirst abdomen\n").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_duplicates() {
+        let err = Wordlist::parse("11111\tabacus\n11112\tabdomen\n11113\tabacus\n").err().expect("wordlist is rejected");
+        assert!(matches!(err, PassphraseError::InvalidWordlist(reason) if reason.contains("'abacus'")));
+    }
+
+    #[test]
+    fn parse_needs_two_words() {
+        assert!(Wordlist::parse("").is_err());
+        assert!(Wordlist::parse("11111\tabacus\n").is_err());
+        assert!(Wordlist::parse("abacus\nabdomen").is_ok());
+    }
+
+    #[test]
+    fn entropy_counts_capitalization_and_digit() {
+        let wordlist = Wordlist::parse("a\nb\nc\nd\n").unwrap();
+        let mut policy = PassphrasePolicy { words: 4, ..PassphrasePolicy::default() };
+        assert_eq!(policy.entropy_bits(&wordlist), 8.0);
+
+        policy.capitalization = Capitalization::First;
+        assert_eq!(policy.entropy_bits(&wordlist), 8.0);
+
+        policy.capitalization = Capitalization::Random;
+        assert_eq!(policy.entropy_bits(&wordlist), 12.0);
+
+        // one of 10 digits on one of 4 words
+        policy.add_digit = true;
+        assert!((policy.entropy_bits(&wordlist) - (12.0 + 40f64.log2())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn generated_passphrase_follows_the_policy() {
+        let wordlist = Wordlist::parse("alpha\nbravo\n").unwrap();
+        let policy = PassphrasePolicy {
+            words: 5,
+            separator: ".".to_string(),
+            capitalization: Capitalization::First,
+            add_digit: true,
+        };
+        let passphrase = generate_passphrase(&policy, &wordlist).unwrap();
+        let words: Vec<&str> = passphrase.passphrase.split('.').collect();
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|w| w.starts_with("Alpha") || w.starts_with("Bravo")));
+        assert_eq!(passphrase.passphrase.chars().filter(char::is_ascii_digit).count(), 1);
+        assert_eq!(passphrase.entropy_bits, policy.entropy_bits(&wordlist));
+    }
+}
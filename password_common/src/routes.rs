@@ -0,0 +1,46 @@
+// Route patterns registered by password_api, together with helpers that
+// build the concrete paths clients request.
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+// Owners and names are free text, so everything but the unreserved characters
+// is escaped; a '/' or '?' in a name would otherwise change the route
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+fn segment(value: &str) -> String {
+    utf8_percent_encode(value, SEGMENT).to_string()
+}
+
+pub const ADD: &str = "/add";
+pub const ENTRIES: &str = "/entries/:owner";
+pub const ENTRY_BY_ID: &str = "/entries/id/:id";
+pub const VERIFY_ENTRY: &str = "/entries/id/:id/verify";
+pub const DELETE_BY_NAME: &str = "/delete/:owner/:name";
+pub const REGISTER: &str = "/register";
+pub const LOGIN: &str = "/login";
+pub const VAULT: &str = "/vault/:owner";
+pub const KEY_CHECK: &str = "/vault/:owner/key-check";
+pub const GENERATE: &str = "/generate";
+
+pub fn entries(owner: &str) -> String {
+    format!("/entries/{}", segment(owner))
+}
+
+pub fn entry_by_id(id: u64) -> String {
+    format!("/entries/id/{}", id)
+}
+
+pub fn verify_entry(id: u64) -> String {
+    format!("/entries/id/{}/verify", id)
+}
+
+pub fn delete_by_name(owner: &str, name: &str) -> String {
+    format!("/delete/{}/{}", segment(owner), segment(name))
+}
+
+pub fn vault(owner: &str) -> String {
+    format!("/vault/{}", segment(owner))
+}
+
+pub fn key_check(owner: &str) -> String {
+    format!("/vault/{}/key-check", segment(owner))
+}
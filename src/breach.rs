@@ -0,0 +1,248 @@
+// Checks passwords against a locally downloaded copy of the Have I Been Pwned
+// Pwned Passwords SHA-1 list, so nothing leaves the machine. Two layouts are
+// understood:
+//
+//   - the single file ordered by hash, one "HASH:COUNT" line per password
+//     (pwned-passwords-sha1-ordered-by-hash-*.txt), which is binary searched
+//     in place rather than read whole;
+//   - a directory of range files as served by the range API, named after the
+//     first five hex digits of the hash ("21BD1.txt") with "SUFFIX:COUNT" lines.
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const HASH_LEN: usize = 40;
+const PREFIX_LEN: usize = 5;
+
+pub struct BreachList {
+    path: PathBuf,
+    ranges: bool,
+}
+
+impl BreachList {
+    // Fails when the path is missing or is not a SHA-1 list
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let metadata = std::fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if metadata.is_dir() {
+            return Ok(BreachList {
+                path: path.to_path_buf(),
+                ranges: true,
+            });
+        }
+
+        let mut first = String::new();
+        BufReader::new(File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?)
+            .read_line(&mut first)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if parse_line(&first).is_none_or(|(hash, _)| hash.len() != HASH_LEN) {
+            return Err(format!(
+                "{} is not a SHA-1 Pwned Passwords file ordered by hash",
+                path.display()
+            ));
+        }
+
+        Ok(BreachList {
+            path: path.to_path_buf(),
+            ranges: false,
+        })
+    }
+
+    // How many times the password appears in known breaches, 0 if never
+    pub fn count(&self, password: &str) -> io::Result<u64> {
+        let hash: String = Sha1::digest(password.as_bytes())
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+
+        if self.ranges {
+            self.count_in_range_file(&hash)
+        } else {
+            self.count_in_sorted_file(&hash)
+        }
+    }
+
+    fn count_in_range_file(&self, hash: &str) -> io::Result<u64> {
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+        let path = self.path.join(format!("{}.txt", prefix));
+        let file = File::open(&path).map_err(|e| {
+            io::Error::new(e.kind(), format!("no range file {}: {}", path.display(), e))
+        })?;
+
+        for line in BufReader::new(file).lines() {
+            if let Some((line_suffix, count)) = parse_line(&line?) {
+                if line_suffix.eq_ignore_ascii_case(suffix) {
+                    return Ok(count);
+                }
+            }
+        }
+        Ok(0)
+    }
+
+    // Binary search over byte offsets for the first line at or after the hash
+    fn count_in_sorted_file(&self, hash: &str) -> io::Result<u64> {
+        let mut file = BufReader::new(File::open(&self.path)?);
+        let len = file.get_ref().metadata()?.len();
+
+        let (mut low, mut high) = (0, len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match line_at(&mut file, mid)? {
+                Some(line) if line_hash(&line).as_str() < hash => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+
+        match line_at(&mut file, low)? {
+            Some(line) if line_hash(&line) == hash => Ok(parse_line(&line).map_or(0, |(_, count)| count)),
+            _ => Ok(0),
+        }
+    }
+}
+
+// The first whole line starting at or after the offset
+fn line_at(file: &mut BufReader<File>, offset: u64) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if offset == 0 {
+        file.seek(SeekFrom::Start(0))?;
+    } else {
+        // Starting one byte early keeps a line beginning exactly at the offset
+        file.seek(SeekFrom::Start(offset - 1))?;
+        file.read_line(&mut line)?;
+        line.clear();
+    }
+
+    if file.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line))
+}
+
+fn line_hash(line: &str) -> String {
+    line.split(':').next().unwrap_or_default().trim().to_ascii_uppercase()
+}
+
+// "HASH:COUNT", with or without a trailing CR as in the downloaded files
+fn parse_line(line: &str) -> Option<(&str, u64)> {
+    let (hash, count) = line.trim_end().split_once(':')?;
+    if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((hash, count.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn sha1(password: &str) -> String {
+        Sha1::digest(password.as_bytes()).iter().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    // "HASH:COUNT" lines for the passwords, ordered by hash, and the
+    // passwords in that order
+    fn sorted_lines(passwords: &[(&str, u64)]) -> (Vec<String>, Vec<String>) {
+        let mut hashed: Vec<(String, &str, u64)> = passwords.iter().map(|(p, n)| (sha1(p), *p, *n)).collect();
+        hashed.sort();
+        let lines = hashed.iter().map(|(hash, _, count)| format!("{}:{}", hash, count)).collect();
+        let order = hashed.iter().map(|(_, password, _)| password.to_string()).collect();
+        (lines, order)
+    }
+
+    const PASSWORDS: &[(&str, u64)] = &[
+        ("password", 9_545_824),
+        ("123456", 37_359_195),
+        ("letmein", 280_232),
+        ("correct horse battery staple", 283),
+        ("hunter2", 27_187),
+        ("trustno1", 144_028),
+    ];
+
+    fn count_of(order: &[String], index: usize) -> u64 {
+        PASSWORDS.iter().find(|(p, _)| *p == order[index]).unwrap().1
+    }
+
+    fn check_sorted_file(separator: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwned.txt");
+        let (lines, order) = sorted_lines(PASSWORDS);
+        fs::write(&path, lines.join(separator) + separator).unwrap();
+
+        let list = BreachList::open(&path).unwrap();
+        let last = order.len() - 1;
+        assert_eq!(list.count(&order[0]).unwrap(), count_of(&order, 0));
+        assert_eq!(list.count(&order[last]).unwrap(), count_of(&order, last));
+        for (index, password) in order.iter().enumerate() {
+            assert_eq!(list.count(password).unwrap(), count_of(&order, index), "{}", password);
+        }
+        assert_eq!(list.count("not in the list").unwrap(), 0);
+    }
+
+    #[test]
+    fn sorted_file_finds_every_line() {
+        check_sorted_file("\n");
+    }
+
+    #[test]
+    fn sorted_file_with_crlf_lines() {
+        check_sorted_file("\r\n");
+    }
+
+    #[test]
+    fn sorted_file_without_a_trailing_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwned.txt");
+        let (lines, order) = sorted_lines(PASSWORDS);
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let list = BreachList::open(&path).unwrap();
+        let last = order.len() - 1;
+        assert_eq!(list.count(&order[last]).unwrap(), count_of(&order, last));
+    }
+
+    #[test]
+    fn hashes_before_and_after_the_file_are_absent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwned.txt");
+        let (lines, order) = sorted_lines(PASSWORDS);
+        let last = order.len() - 1;
+        fs::write(&path, lines[1..last].join("\n") + "\n").unwrap();
+
+        let list = BreachList::open(&path).unwrap();
+        assert_eq!(list.count(&order[0]).unwrap(), 0);
+        assert_eq!(list.count(&order[last]).unwrap(), 0);
+        assert_eq!(list.count(&order[1]).unwrap(), count_of(&order, 1));
+    }
+
+    #[test]
+    fn range_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let write_range = |password: &str, listed: bool| {
+            let hash = sha1(password);
+            let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+            let mut text = "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n".to_string();
+            if listed {
+                text += &format!("{}:42\r\n", suffix.to_ascii_lowercase());
+            }
+            fs::write(dir.path().join(format!("{}.txt", prefix)), text).unwrap();
+        };
+        write_range("password", true);
+        write_range("letmein", false);
+
+        let list = BreachList::open(dir.path()).unwrap();
+        assert_eq!(list.count("password").unwrap(), 42);
+        assert_eq!(list.count("letmein").unwrap(), 0);
+        // A range with no file is an error rather than a miss
+        assert!(list.count("hunter2").is_err());
+    }
+
+    #[test]
+    fn open_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "not a hash list\n").unwrap();
+        assert!(BreachList::open(&path).is_err());
+        assert!(BreachList::open(&dir.path().join("missing.txt")).is_err());
+    }
+}
@@ -0,0 +1,567 @@
+use clap::{Args, Parser, Subcommand};
+use password_client::{ClientError, PasswordApiClient};
+use password_common::generator::DEFAULT_LENGTH;
+use password_common::passphrase::{Capitalization, DEFAULT_SEPARATOR, DEFAULT_WORDS};
+use password_common::{
+    generate_passphrase, generate_password, AddEntry, Entry, Passphrase, PassphrasePolicy, PasswordPolicy, VerifyResult,
+    Wordlist,
+};
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use crate::hashPassword::hash_password;
+use crate::config::Profile;
+use crate::local;
+use crate::secret::{prompt_hidden, SecretString};
+use crate::strength::{self, Shortfall, Verdict};
+use crate::vault::{self, Vault};
+
+// Exit codes for scripted use; clap itself exits with 2 on usage errors
+pub const EXIT_FAILURE: u8 = 1;
+pub const EXIT_LOCKED: u8 = 3;
+pub const EXIT_WRONG_PASSWORD: u8 = 4;
+pub const EXIT_NOT_FOUND: u8 = 5;
+pub const EXIT_WEAK_PASSWORD: u8 = 6;
+
+#[derive(Parser)]
+#[command(name = "my_app", about = "AP's Password Manager", version)]
+pub struct Cli {
+    /// Client config profile to use [env: APS_PROFILE]
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Run password_api inside this process with a local SQLite database
+    #[arg(long, global = true)]
+    pub local: bool,
+
+    /// Database file used by --local
+    #[arg(long, global = true, default_value = local::DEFAULT_DB_PATH)]
+    pub local_db: String,
+
+    /// Without a subcommand the interactive menu is started
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List an owner's entries as tab separated id, name, username and last update
+    List {
+        /// Defaults to the profile's default_owner
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    /// Add a new entry
+    Add {
+        /// Defaults to the profile's default_owner
+        #[arg(long)]
+        owner: Option<String>,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        username: String,
+        /// Store a generated password instead of reading one, and print it
+        #[arg(long, conflicts_with_all = ["password_stdin", "password_fd"])]
+        generate: bool,
+        /// Store a generated passphrase instead of reading a password, and print it
+        #[arg(long, conflicts_with_all = ["password_stdin", "password_fd", "generate"])]
+        passphrase: bool,
+        #[command(flatten)]
+        policy: PolicyArgs,
+        #[command(flatten)]
+        passphrase_policy: PassphraseArgs,
+        #[command(flatten)]
+        input: PasswordInput,
+    },
+    /// Print a random password; nothing is stored and no server is needed
+    Generate {
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Print a random passphrase and, on stderr, its entropy; nothing is stored
+    Passphrase {
+        #[command(flatten)]
+        policy: PassphraseArgs,
+    },
+    /// Check a password against a stored entry (exit code 4 when it does not match)
+    Verify {
+        #[command(flatten)]
+        entry: EntrySelector,
+        #[command(flatten)]
+        input: PasswordInput,
+    },
+    /// Delete an entry after confirming its password
+    Delete {
+        #[command(flatten)]
+        entry: EntrySelector,
+        #[command(flatten)]
+        input: PasswordInput,
+    },
+    /// Print an entry
+    Get {
+        #[command(flatten)]
+        entry: EntrySelector,
+        /// Also decrypt and print the stored password
+        #[arg(long)]
+        reveal: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct EntrySelector {
+    /// Defaults to the profile's default_owner
+    #[arg(long)]
+    owner: Option<String>,
+    /// Id of the entry, as printed by list
+    #[arg(long, required_unless_present = "name", conflicts_with = "name")]
+    id: Option<u64>,
+    /// Name of the entry; fails if several entries share it
+    #[arg(long)]
+    name: Option<String>,
+}
+
+#[derive(Args)]
+pub struct PasswordInput {
+    /// Read the account password from the first line of stdin instead of the terminal
+    #[arg(long)]
+    password_stdin: bool,
+    /// Read the account password from the first line of this open file
+    /// descriptor, e.g. --password-fd 3 with 3<secret.txt
+    #[arg(long, value_name = "FD", conflicts_with = "password_stdin")]
+    password_fd: Option<u32>,
+}
+
+// Options for generated passwords, used by generate and add --generate
+#[derive(Args)]
+pub struct PolicyArgs {
+    /// Number of characters in a generated password
+    #[arg(long, default_value_t = DEFAULT_LENGTH)]
+    length: usize,
+    /// Leave lowercase letters out of a generated password
+    #[arg(long)]
+    no_lowercase: bool,
+    /// Leave uppercase letters out of a generated password
+    #[arg(long)]
+    no_uppercase: bool,
+    /// Leave digits out of a generated password
+    #[arg(long)]
+    no_digits: bool,
+    /// Leave symbols out of a generated password
+    #[arg(long)]
+    no_symbols: bool,
+    /// Leave out characters that are easy to confuse (I, l, 1, O, 0, o)
+    #[arg(long)]
+    exclude_ambiguous: bool,
+    /// Do not require a character from every enabled class
+    #[arg(long)]
+    no_require_each: bool,
+}
+
+impl PolicyArgs {
+    fn generate(&self) -> Result<String, ExitCode> {
+        let policy = PasswordPolicy {
+            length: self.length,
+            lowercase: !self.no_lowercase,
+            uppercase: !self.no_uppercase,
+            digits: !self.no_digits,
+            symbols: !self.no_symbols,
+            exclude_ambiguous: self.exclude_ambiguous,
+            require_each_class: !self.no_require_each,
+        };
+
+        generate_password(&policy).map_err(|e| {
+            eprintln!("Cannot generate a password: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        })
+    }
+}
+
+// Options for generated passphrases, used by passphrase and add --passphrase
+#[derive(Args)]
+pub struct PassphraseArgs {
+    /// Number of words in a generated passphrase
+    #[arg(long, default_value_t = DEFAULT_WORDS)]
+    words: usize,
+    /// Placed between the words of a generated passphrase
+    #[arg(long, default_value = DEFAULT_SEPARATOR)]
+    separator: String,
+    /// Capitalize the first letter of no word, every word, or words at random
+    #[arg(long, value_name = "none|first|random", default_value = "none")]
+    capitalize: Capitalization,
+    /// Append a random digit to one of the words
+    #[arg(long)]
+    add_digit: bool,
+    /// Draw words from this file (one per line, optionally after dice rolls
+    /// as in the EFF lists) instead of the profile's wordlist or the bundled one
+    #[arg(long)]
+    wordlist: Option<PathBuf>,
+}
+
+impl PassphraseArgs {
+    // --wordlist wins over the profile's list
+    fn generate(&self, profile_wordlist: &Wordlist) -> Result<Passphrase, ExitCode> {
+        let loaded;
+        let wordlist = match &self.wordlist {
+            Some(path) => {
+                loaded = Wordlist::load(path).map_err(|e| {
+                    eprintln!("{}", e);
+                    ExitCode::from(EXIT_FAILURE)
+                })?;
+                &loaded
+            }
+            None => profile_wordlist,
+        };
+
+        let policy = PassphrasePolicy {
+            words: self.words,
+            separator: self.separator.clone(),
+            capitalization: self.capitalize,
+            add_digit: self.add_digit,
+        };
+
+        generate_passphrase(&policy, wordlist).map_err(|e| {
+            eprintln!("Cannot generate a passphrase: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        })
+    }
+}
+
+// Trimmed in place and sized up front so no copy of the password is left behind
+fn first_line(mut reader: impl BufRead) -> io::Result<SecretString> {
+    let mut line = SecretString::from(String::with_capacity(256));
+    let buf = line.buffer();
+    reader.read_line(buf)?;
+    let len = buf.trim_end_matches(['\r', '\n']).len();
+    buf.truncate(len);
+    Ok(line)
+}
+
+// Where add takes the account password from
+enum NewPassword<'a> {
+    Read(&'a PasswordInput),
+    Generate(&'a PolicyArgs),
+    Passphrase(&'a PassphraseArgs),
+}
+
+impl PasswordInput {
+    fn read(&self, prompt: &str) -> Result<SecretString, ExitCode> {
+        let result = if self.password_stdin {
+            first_line(io::stdin().lock())
+        } else if let Some(fd) = self.password_fd {
+            // /dev/fd avoids taking ownership of a raw descriptor
+            std::fs::File::open(format!("/dev/fd/{}", fd)).and_then(|f| first_line(io::BufReader::new(f)))
+        } else {
+            prompt_hidden(prompt)
+        };
+
+        result.map_err(|e| {
+            eprintln!("Failed to read password: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        })
+    }
+
+    // Like read, but a password typed at the terminal has to be typed twice
+    fn read_new(&self, prompt: &str) -> Result<SecretString, ExitCode> {
+        let password = self.read(prompt)?;
+        if password.is_empty() {
+            eprintln!("The password cannot be empty.");
+            return Err(ExitCode::from(EXIT_FAILURE));
+        }
+        if self.password_stdin || self.password_fd.is_some() {
+            return Ok(password);
+        }
+
+        if self.read("Confirm the password: ")? != password {
+            eprintln!("Passwords do not match.");
+            return Err(ExitCode::from(EXIT_FAILURE));
+        }
+        Ok(password)
+    }
+}
+
+pub async fn run(command: Command, client: &mut PasswordApiClient, profile: &Profile) -> ExitCode {
+    let result = match command {
+        Command::List { owner } => list(client, profile, owner.as_deref()).await,
+        Command::Add {
+            owner,
+            name,
+            username,
+            generate,
+            passphrase,
+            policy,
+            passphrase_policy,
+            input,
+        } => {
+            let source = if generate {
+                NewPassword::Generate(&policy)
+            } else if passphrase {
+                NewPassword::Passphrase(&passphrase_policy)
+            } else {
+                NewPassword::Read(&input)
+            };
+            add(client, profile, owner.as_deref(), name, username, source).await
+        }
+        Command::Generate { policy } => policy.generate().map(|password| println!("{}", password)),
+        Command::Passphrase { policy } => policy.generate(&profile.wordlist).map(|generated| {
+            println!("{}", generated.passphrase);
+            eprintln!("Entropy: {:.1} bits", generated.entropy_bits);
+        }),
+        Command::Verify { entry, input } => verify(client, profile, &entry, &input).await,
+        Command::Delete { entry, input } => delete(client, profile, &entry, &input).await,
+        Command::Get { entry, reveal } => get(client, profile, &entry, reveal).await,
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(code) => code,
+    }
+}
+
+// The master password comes from APS_MASTER_PASSWORD when set, otherwise
+// from the terminal, so stdin stays free for the account password. Without
+// --owner the profile's default owner is used.
+async fn open_vault(client: &mut PasswordApiClient, profile: &Profile, owner: Option<&str>) -> Result<Vault, ExitCode> {
+    let owner = match owner.or(profile.default_owner.as_deref()) {
+        Some(o) => o,
+        None => {
+            eprintln!("No owner given; use --owner or set default_owner in profile '{}'.", profile.name);
+            return Err(ExitCode::from(EXIT_FAILURE));
+        }
+    };
+
+    let master_password = match std::env::var("APS_MASTER_PASSWORD") {
+        Ok(p) => SecretString::from(p),
+        Err(_) => prompt_hidden("Master password: ").map_err(|e| {
+            eprintln!("Failed to read master password: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        })?,
+    };
+
+    vault::unlock_with_password(client, owner, &master_password, profile.token_file.as_deref(), &profile.hashing)
+        .await
+        .ok_or(ExitCode::from(EXIT_LOCKED))
+}
+
+// Reports a failed API call and picks the matching exit code
+fn client_failure(e: ClientError) -> ExitCode {
+    eprintln!("{}", e);
+    if e.is_not_found() {
+        ExitCode::from(EXIT_NOT_FOUND)
+    } else {
+        ExitCode::from(EXIT_FAILURE)
+    }
+}
+
+async fn fetch_entries(client: &PasswordApiClient, vault: &Vault) -> Result<Vec<Entry>, ExitCode> {
+    client.list_entries(&vault.owner).await.map_err(client_failure)
+}
+
+async fn select_entry(client: &PasswordApiClient, vault: &Vault, selector: &EntrySelector) -> Result<Entry, ExitCode> {
+    if let Some(id) = selector.id {
+        return client.get_entry(id).await.map_err(client_failure);
+    }
+
+    let name = selector.name.as_deref().unwrap_or_default();
+    let mut matches: Vec<Entry> = fetch_entries(client, vault)
+        .await?
+        .into_iter()
+        .filter(|e| e.account_name.as_deref() == Some(name))
+        .collect();
+
+    match matches.len() {
+        1 => Ok(matches.remove(0)),
+        0 => {
+            eprintln!("No entry named {}.", name);
+            Err(ExitCode::from(EXIT_NOT_FOUND))
+        }
+        n => {
+            eprintln!("{} entries are named {}; select one with --id.", n, name);
+            Err(ExitCode::from(EXIT_FAILURE))
+        }
+    }
+}
+
+// The password read, once the server confirms it matches the entry, and
+// what the server said about the stored hash
+async fn check_password(
+    client: &PasswordApiClient,
+    entry: &Entry,
+    input: &PasswordInput,
+) -> Result<(SecretString, VerifyResult), ExitCode> {
+    let assumed = input.read("Account password: ")?;
+    match client.verify_entry(entry.id, &assumed).await {
+        Ok(result) if result.matches => Ok((assumed, result)),
+        Ok(_) => {
+            eprintln!("Incorrect password.");
+            Err(ExitCode::from(EXIT_WRONG_PASSWORD))
+        }
+        Err(e) if e.is_unprocessable() => {
+            eprintln!(
+                "Cannot check entry {}. {}. Select it in the interactive client to repair or remove it.",
+                entry.id, e
+            );
+            Err(ExitCode::from(EXIT_FAILURE))
+        }
+        Err(e) => Err(client_failure(e)),
+    }
+}
+
+async fn list(client: &mut PasswordApiClient, profile: &Profile, owner: Option<&str>) -> Result<(), ExitCode> {
+    let vault = open_vault(client, profile, owner).await?;
+
+    for entry in fetch_entries(client, &vault).await? {
+        println!(
+            "{}\t{}\t{}\t{}",
+            entry.id,
+            entry.account_name.unwrap_or_default(),
+            entry.account_username.unwrap_or_default(),
+            entry.updated_at.to_rfc3339()
+        );
+    }
+
+    Ok(())
+}
+
+async fn add(
+    client: &mut PasswordApiClient,
+    profile: &Profile,
+    owner: Option<&str>,
+    name: String,
+    username: String,
+    source: NewPassword<'_>,
+) -> Result<(), ExitCode> {
+    let vault = open_vault(client, profile, owner).await?;
+    let account_password = match source {
+        NewPassword::Read(input) => input.read_new("Account password: ")?,
+        NewPassword::Generate(policy) => {
+            let password = SecretString::from(policy.generate()?);
+            println!("password: {}", password.expose());
+            password
+        }
+        NewPassword::Passphrase(policy) => {
+            let generated = policy.generate(&profile.wordlist)?;
+            println!("password: {}", generated.passphrase);
+            println!("entropy: {:.1} bits", generated.entropy_bits);
+            SecretString::from(generated.passphrase)
+        }
+    };
+
+    // Scripts only hear about the estimate when the password falls short
+    let assessment = strength::assess(
+        account_password.expose(),
+        &[&vault.owner, &name, &username],
+        &profile.strength,
+        profile.breach_list.as_ref(),
+    );
+    report_breaches(&assessment.breaches);
+    if let Verdict::Warn(shortfall) | Verdict::Refuse(shortfall) = assessment.verdict {
+        if let Shortfall::BelowMinimum(_) = shortfall {
+            eprintln!("{}", assessment.estimate);
+        }
+        if let Verdict::Refuse(_) = assessment.verdict {
+            eprintln!("Password refused: it {}.", shortfall);
+            return Err(ExitCode::from(EXIT_WEAK_PASSWORD));
+        }
+        eprintln!("Saving it anyway, although it {}.", shortfall);
+    }
+
+    let hashed_password = hash_password(&account_password, &profile.hashing).await.map_err(|e| {
+        eprintln!("{}.", e);
+        ExitCode::from(EXIT_FAILURE)
+    })?;
+    let encrypted = vault.encrypt(&account_password);
+    let new_entry = AddEntry {
+        owner: vault.owner.clone(),
+        name,
+        username,
+        password: hashed_password,
+        ciphertext: encrypted.ciphertext,
+        nonce: encrypted.nonce,
+    };
+
+    // Scripts can pass the id on to get, verify and delete --id
+    let entry = client.add_entry(&new_entry).await.map_err(client_failure)?;
+    println!("Entry added.");
+    println!("id: {}", entry.id);
+    Ok(())
+}
+
+async fn verify(
+    client: &mut PasswordApiClient,
+    profile: &Profile,
+    selector: &EntrySelector,
+    input: &PasswordInput,
+) -> Result<(), ExitCode> {
+    let vault = open_vault(client, profile, selector.owner.as_deref()).await?;
+    let entry = select_entry(client, &vault, selector).await?;
+
+    let (password, verified) = check_password(client, &entry, input).await?;
+    println!("Correct password.");
+    let stored = verified.hash_parameters.as_deref();
+    match vault::upgrade_hash(client, &vault, entry.id, stored, &password, &profile.hashing).await {
+        Ok(true) => eprintln!("Upgraded the stored hash to the current parameters."),
+        Ok(false) => {}
+        Err(e) => eprintln!("Could not upgrade the stored hash: {}", e),
+    }
+    report_breaches(&profile.breach_list.as_ref().map(|list| list.count(password.expose())));
+    Ok(())
+}
+
+// Only a hit or a failed lookup is reported, on stderr
+fn report_breaches(breaches: &Option<io::Result<u64>>) {
+    match breaches {
+        Some(Ok(count)) if *count > 0 => {
+            eprintln!("Warning: this password has appeared {} times in known data breaches.", count);
+        }
+        Some(Err(e)) => eprintln!("Could not check the breached password list: {}", e),
+        _ => {}
+    }
+}
+
+async fn delete(
+    client: &mut PasswordApiClient,
+    profile: &Profile,
+    selector: &EntrySelector,
+    input: &PasswordInput,
+) -> Result<(), ExitCode> {
+    let vault = open_vault(client, profile, selector.owner.as_deref()).await?;
+    let entry = select_entry(client, &vault, selector).await?;
+
+    check_password(client, &entry, input).await?;
+    client.delete_entry(entry.id).await.map_err(client_failure)?;
+    println!("Entry deleted.");
+    Ok(())
+}
+
+async fn get(
+    client: &mut PasswordApiClient,
+    profile: &Profile,
+    selector: &EntrySelector,
+    reveal: bool,
+) -> Result<(), ExitCode> {
+    let vault = open_vault(client, profile, selector.owner.as_deref()).await?;
+    let entry = select_entry(client, &vault, selector).await?;
+
+    println!("id: {}", entry.id);
+    println!("name: {}", entry.account_name.clone().unwrap_or_default());
+    println!("username: {}", entry.account_username.clone().unwrap_or_default());
+    println!("created: {}", entry.created_at.to_rfc3339());
+    println!("updated: {}", entry.updated_at.to_rfc3339());
+
+    if reveal {
+        let password = match (&entry.account_ciphertext, &entry.account_nonce) {
+            (Some(c), Some(n)) => vault.decrypt(c, n),
+            _ => None,
+        };
+        match password {
+            Some(p) => println!("password: {}", p.expose()),
+            None => {
+                eprintln!("Could not decrypt the stored password.");
+                return Err(ExitCode::from(EXIT_FAILURE));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use password_client::{parse_fingerprint, TlsTrust};
+use password_common::Wordlist;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::breach::BreachList;
+use crate::hashPassword::HashParams;
+use crate::strength::{Enforcement, StrengthPolicy, MAX_SCORE};
+
+pub const DEFAULT_SERVER_URL: &str = "http://127.0.0.1:3000";
+pub const DEFAULT_PROFILE: &str = "default";
+
+// Where the client config lives unless APS_CLIENT_CONFIG points elsewhere
+const CONFIG_DIR: &str = "aps-password-manager";
+const CONFIG_FILE: &str = "client.toml";
+
+// Layout of the client config file, for example
+//
+//   default_profile = "dev"
+//
+//   [profiles.dev]
+//   server_url = "http://127.0.0.1:3000"
+//   default_owner = "alice"
+//   token_file = "~/.cache/aps-password-manager/dev.token"
+//
+//   [profiles.team]
+//   server_url = "https://passwords.example.com"
+//   ca_file = "~/.config/aps-password-manager/team-ca.pem"
+//   min_password_score = 4
+//   weak_passwords = "refuse"
+//   breach_file = "~/hibp/pwned-passwords-sha1-ordered-by-hash-v8.txt"
+//   hash_memory_kib = 65536
+//   hash_iterations = 3
+//   wordlist = "~/.config/aps-password-manager/words.txt"
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    default_profile: Option<String>,
+    profiles: HashMap<String, ProfileSettings>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ProfileSettings {
+    server_url: Option<String>,
+    default_owner: Option<String>,
+    token_file: Option<String>,
+    // PEM CA certificates to trust instead of the usual roots
+    ca_file: Option<String>,
+    // SHA-256 fingerprint of the one server certificate to trust
+    cert_fingerprint: Option<String>,
+    // 0 to 4; new passwords scoring lower are warned about or refused
+    min_password_score: Option<u8>,
+    // "warn" or "refuse"
+    weak_passwords: Option<String>,
+    // Downloaded Pwned Passwords SHA-1 file, or directory of range files
+    breach_file: Option<String>,
+    // "argon2id", "argon2i" or "argon2d"
+    hash_algorithm: Option<String>,
+    // Argon2 costs for account password hashes
+    hash_memory_kib: Option<u32>,
+    hash_iterations: Option<u32>,
+    hash_parallelism: Option<u32>,
+    // Passphrases are drawn from this list instead of the bundled one
+    wordlist: Option<String>,
+}
+
+// The server and defaults the client works with
+pub struct Profile {
+    pub name: String,
+    pub server_url: String,
+    // Used when no owner is given on the command line
+    pub default_owner: Option<String>,
+    // Session tokens are kept here between runs when set
+    pub token_file: Option<PathBuf>,
+    // Which certificates an https server_url may present
+    pub tls: TlsTrust,
+    // How weak new account passwords are handled
+    pub strength: StrengthPolicy,
+    // New and verified passwords are looked up here when set
+    pub breach_list: Option<BreachList>,
+    // Costs for new account password hashes; older hashes are upgraded to
+    // them when their password is next verified
+    pub hashing: HashParams,
+    // Passphrases are drawn from this list
+    pub wordlist: Wordlist,
+}
+
+impl Profile {
+    fn builtin() -> Self {
+        Profile {
+            name: DEFAULT_PROFILE.to_string(),
+            server_url: DEFAULT_SERVER_URL.to_string(),
+            default_owner: None,
+            token_file: None,
+            tls: TlsTrust::default(),
+            strength: StrengthPolicy::default(),
+            breach_list: None,
+            hashing: HashParams::default(),
+            wordlist: Wordlist::bundled(),
+        }
+    }
+}
+
+// $APS_CLIENT_CONFIG, else $XDG_CONFIG_HOME/aps-password-manager/client.toml,
+// else ~/.config/aps-password-manager/client.toml
+pub fn config_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("APS_CLIENT_CONFIG") {
+        return Some(expand_home(&path));
+    }
+
+    let base = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var("HOME").ok()?).join(".config"),
+    };
+    Some(base.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+// Picks the profile named by --profile, then APS_PROFILE, then the file's
+// default_profile. Without a config file only the built-in default profile exists.
+pub fn load_profile(requested: Option<&str>) -> Result<Profile, String> {
+    let env_profile = std::env::var("APS_PROFILE").ok().filter(|p| !p.is_empty());
+    let requested = requested.map(str::to_string).or(env_profile);
+
+    let path = config_path();
+    let file = match &path {
+        Some(p) if p.exists() => read_config(p)?,
+        _ => ConfigFile::default(),
+    };
+
+    let name = requested
+        .or(file.default_profile)
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+
+    let settings = match file.profiles.get(&name) {
+        Some(s) => s,
+        None if name == DEFAULT_PROFILE => return Ok(Profile::builtin()),
+        None => {
+            let location = path.map(|p| p.display().to_string()).unwrap_or_default();
+            return Err(format!("No profile named '{}' in {}", name, location));
+        }
+    };
+
+    let server_url = settings
+        .server_url
+        .clone()
+        .unwrap_or_else(|| DEFAULT_SERVER_URL.to_string());
+    if !server_url.starts_with("http://") && !server_url.starts_with("https://") {
+        return Err(format!(
+            "Profile '{}' has server_url '{}', which must start with http:// or https://",
+            name, server_url
+        ));
+    }
+
+    let tls = tls_trust(&name, settings)?;
+    let strength = strength_policy(&name, settings)?;
+    let breach_list = match &settings.breach_file {
+        Some(path) => Some(
+            BreachList::open(&expand_home(path))
+                .map_err(|e| format!("Profile '{}' has an unusable breach_file: {}", name, e))?,
+        ),
+        None => None,
+    };
+    let hashing = hash_params(&name, settings)?;
+    let wordlist = match &settings.wordlist {
+        Some(path) => Wordlist::load(&expand_home(path))
+            .map_err(|e| format!("Profile '{}' has an unusable wordlist: {}", name, e))?,
+        None => Wordlist::bundled(),
+    };
+
+    Ok(Profile {
+        server_url,
+        default_owner: settings.default_owner.clone(),
+        token_file: settings.token_file.as_deref().map(expand_home),
+        tls,
+        strength,
+        breach_list,
+        hashing,
+        wordlist,
+        name,
+    })
+}
+
+fn strength_policy(name: &str, settings: &ProfileSettings) -> Result<StrengthPolicy, String> {
+    let mut policy = StrengthPolicy::default();
+
+    if let Some(score) = settings.min_password_score {
+        if score > MAX_SCORE {
+            return Err(format!(
+                "Profile '{}' has min_password_score {}, expected 0 to {}",
+                name, score, MAX_SCORE
+            ));
+        }
+        policy.min_score = score;
+    }
+
+    policy.enforcement = match settings.weak_passwords.as_deref() {
+        None | Some("warn") => Enforcement::Warn,
+        Some("refuse") => Enforcement::Refuse,
+        Some(other) => {
+            return Err(format!(
+                "Profile '{}' has weak_passwords '{}', expected warn or refuse",
+                name, other
+            ))
+        }
+    };
+
+    Ok(policy)
+}
+
+// Unset costs keep the defaults
+fn hash_params(name: &str, settings: &ProfileSettings) -> Result<HashParams, String> {
+    let defaults = HashParams::default();
+    let algorithm = match settings.hash_algorithm.as_deref() {
+        None => defaults.algorithm,
+        Some(algorithm) => algorithm.parse().map_err(|_| {
+            format!(
+                "Profile '{}' has hash_algorithm '{}', expected argon2id, argon2i or argon2d",
+                name, algorithm
+            )
+        })?,
+    };
+
+    HashParams::new(
+        algorithm,
+        settings.hash_memory_kib.unwrap_or(defaults.memory_kib()),
+        settings.hash_iterations.unwrap_or(defaults.iterations()),
+        settings.hash_parallelism.unwrap_or(defaults.parallelism()),
+    )
+    .map_err(|e| format!("Profile '{}' has unusable hash costs: {}", name, e))
+}
+
+// A profile may pin a CA or a certificate fingerprint, not both
+fn tls_trust(name: &str, settings: &ProfileSettings) -> Result<TlsTrust, String> {
+    match (&settings.ca_file, &settings.cert_fingerprint) {
+        (None, None) => Ok(TlsTrust::WebPki),
+        (Some(_), Some(_)) => Err(format!(
+            "Profile '{}' sets both ca_file and cert_fingerprint; use one",
+            name
+        )),
+        (Some(ca_file), None) => {
+            let path = expand_home(ca_file);
+            std::fs::read(&path)
+                .map(TlsTrust::CaCertificates)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+        }
+        (None, Some(fingerprint)) => parse_fingerprint(fingerprint)
+            .map(TlsTrust::Fingerprint)
+            .ok_or_else(|| {
+                format!(
+                    "Profile '{}' has cert_fingerprint '{}', expected 64 hex digits",
+                    name, fingerprint
+                )
+            }),
+    }
+}
+
+fn read_config(path: &Path) -> Result<ConfigFile, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+// Allows "~/..." in paths
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
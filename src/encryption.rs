@@ -0,0 +1,203 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit},
+    XChaCha20Poly1305, XNonce,
+};
+use hmac::{Hmac, Mac};
+use password_common::{DEFAULT_KDF_ITERATIONS, DEFAULT_KDF_MEMORY_KIB, DEFAULT_KDF_PARALLELISM};
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::hashPassword::run_on_pool;
+use crate::secret::SecretString;
+
+pub const KEY_LEN: usize = 32;
+pub const SALT_LEN: usize = 16;
+
+// Argon2id cost parameters, stored in the vault header so they can be raised later
+#[derive(Debug, Clone, Copy)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: DEFAULT_KDF_MEMORY_KIB,
+            iterations: DEFAULT_KDF_ITERATIONS,
+            parallelism: DEFAULT_KDF_PARALLELISM,
+        }
+    }
+}
+
+// Ciphertext and nonce are base64 encoded so they can travel as JSON strings
+pub struct EncryptedPassword {
+    pub ciphertext: String,
+    pub nonce: String,
+}
+
+pub fn generate_salt() -> String {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    STANDARD.encode(salt)
+}
+
+// Returns None when the salt or parameters are not usable by Argon2
+pub async fn derive_key(master_password: &SecretString, salt: &str, kdf: &KdfParams) -> Option<[u8; KEY_LEN]> {
+    let (master_password, salt, kdf) = (master_password.clone(), salt.to_string(), *kdf);
+    run_on_pool(move || derive_key_blocking(&master_password, &salt, &kdf)).await
+}
+
+// The key clients used before vaults had their own salt and costs: Argon2
+// defaults salted with the owner name. Only needed to re-encrypt records
+// saved back then.
+pub async fn derive_legacy_key(master_password: &SecretString, owner: &str) -> [u8; KEY_LEN] {
+    let (master_password, salt) = (master_password.clone(), format!("aps-password-manager:{}", owner));
+    run_on_pool(move || {
+        let mut key = [0u8; KEY_LEN];
+        Argon2::default()
+            .hash_password_into(master_password.expose().as_bytes(), salt.as_bytes(), &mut key)
+            .expect("the legacy salt and default costs are valid");
+        key
+    })
+    .await
+}
+
+fn derive_key_blocking(master_password: &SecretString, salt: &str, kdf: &KdfParams) -> Option<[u8; KEY_LEN]> {
+    let salt = STANDARD.decode(salt).ok()?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(KEY_LEN)).ok()?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let mut key = [0u8; KEY_LEN];
+    argon2
+        .hash_password_into(master_password.expose().as_bytes(), &salt, &mut key)
+        .ok()?;
+
+    Some(key)
+}
+
+// The server authenticates owners with a secret derived from the vault key,
+// so the master password itself never leaves the client.
+pub fn derive_login_secret(key: &[u8; KEY_LEN]) -> SecretString {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(b"aps-password-manager-login");
+    let mut bytes = mac.finalize().into_bytes();
+    let secret = SecretString::from(STANDARD.encode(bytes));
+    bytes.as_mut_slice().zeroize();
+    secret
+}
+
+pub fn encrypt_password(key: &[u8; KEY_LEN], password: &str) -> EncryptedPassword {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, password.as_bytes())
+        .expect("Failed to encrypt password");
+
+    EncryptedPassword {
+        ciphertext: STANDARD.encode(ciphertext),
+        nonce: STANDARD.encode(nonce),
+    }
+}
+
+// Returns None when the data is malformed or the key is wrong
+pub fn decrypt_password(key: &[u8; KEY_LEN], ciphertext: &str, nonce: &str) -> Option<SecretString> {
+    let ciphertext = STANDARD.decode(ciphertext).ok()?;
+    let nonce = STANDARD.decode(nonce).ok()?;
+    if nonce.len() != 24 {
+        return None;
+    }
+
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+        .ok()?;
+
+    // On invalid UTF-8 the bytes come back in the error, to be wiped too
+    match String::from_utf8(plaintext) {
+        Ok(password) => Some(SecretString::from(password)),
+        Err(e) => {
+            e.into_bytes().zeroize();
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; KEY_LEN] = [7; KEY_LEN];
+    const PASSWORD: &str = "Tq9#vLm2!xZr8pWe";
+
+    #[test]
+    fn encrypted_passwords_decrypt_with_the_same_key() {
+        let encrypted = encrypt_password(&KEY, PASSWORD);
+        let decrypted = decrypt_password(&KEY, &encrypted.ciphertext, &encrypted.nonce).unwrap();
+        assert_eq!(decrypted.expose(), PASSWORD);
+
+        // A fresh nonce every time
+        let again = encrypt_password(&KEY, PASSWORD);
+        assert_ne!(encrypted.nonce, again.nonce);
+        assert_ne!(encrypted.ciphertext, again.ciphertext);
+    }
+
+    #[test]
+    fn wrong_key_or_malformed_data_decrypts_to_none() {
+        let encrypted = encrypt_password(&KEY, PASSWORD);
+        assert!(decrypt_password(&[8; KEY_LEN], &encrypted.ciphertext, &encrypted.nonce).is_none());
+
+        let short_nonce = STANDARD.encode([0u8; 12]);
+        assert!(decrypt_password(&KEY, &encrypted.ciphertext, &short_nonce).is_none());
+        assert!(decrypt_password(&KEY, &encrypted.ciphertext, "not base64!").is_none());
+        assert!(decrypt_password(&KEY, "not base64!", &encrypted.nonce).is_none());
+
+        let mut tampered = STANDARD.decode(&encrypted.ciphertext).unwrap();
+        tampered[0] ^= 1;
+        assert!(decrypt_password(&KEY, &STANDARD.encode(tampered), &encrypted.nonce).is_none());
+    }
+
+    // Made by the client from before vaults existed, which derived the key
+    // with Argon2 defaults salted with the owner name
+    #[tokio::test]
+    async fn legacy_key_opens_records_from_older_clients() {
+        let master = SecretString::from("correct horse battery staple".to_string());
+        let ciphertext = "eCPL9rrY/ZQ+z6M/l0htaW72KZBNWSuNl3DA7TEQq8g=";
+        let nonce = "JFhBmhBQrZG1xHz8T+5h7e2KAWiyUJmC";
+
+        let key = derive_legacy_key(&master, "alice").await;
+        assert_eq!(decrypt_password(&key, ciphertext, nonce).unwrap().expose(), PASSWORD);
+
+        let other_owner = derive_legacy_key(&master, "bob").await;
+        assert!(decrypt_password(&other_owner, ciphertext, nonce).is_none());
+    }
+
+    #[tokio::test]
+    async fn vault_keys_depend_on_salt_and_costs() {
+        let master = SecretString::from("correct horse battery staple".to_string());
+        let kdf = KdfParams {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let salt = generate_salt();
+
+        let key = derive_key(&master, &salt, &kdf).await.unwrap();
+        assert_eq!(derive_key(&master, &salt, &kdf).await, Some(key));
+        assert_ne!(derive_key(&master, &generate_salt(), &kdf).await, Some(key));
+        let costlier = KdfParams { iterations: 2, ..kdf };
+        assert_ne!(derive_key(&master, &salt, &costlier).await, Some(key));
+
+        assert!(derive_key(&master, "not base64!", &kdf).await.is_none());
+        let no_memory = KdfParams { memory_kib: 0, ..kdf };
+        assert!(derive_key(&master, &salt, &no_memory).await.is_none());
+
+        assert_eq!(derive_login_secret(&key), derive_login_secret(&key));
+        assert_ne!(derive_login_secret(&key), derive_login_secret(&KEY));
+    }
+}
@@ -1,25 +1,183 @@
 use argon2::{
-    Argon2,
-    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+    password_hash::{self, PasswordHash, PasswordHasher, SaltString},
 };
 use rand::rngs::OsRng; // <- use rand crate, not rand_core
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::Semaphore;
 
-pub fn hash_password(password: &str) -> String {
-    let salt = SaltString::generate(&mut OsRng);
+use crate::secret::SecretString;
+
+// Argon2 variant and costs for new account password hashes. Existing hashes
+// carry their own parameters, so these can be raised at any time.
+#[derive(Debug, Clone)]
+pub struct HashParams {
+    pub algorithm: Algorithm,
+    params: Params,
+}
+
+impl Default for HashParams {
+    fn default() -> Self {
+        HashParams {
+            algorithm: Algorithm::Argon2id,
+            params: Params::default(),
+        }
+    }
+}
 
-    let argon2 = Argon2::default();
+impl HashParams {
+    // Fails when Argon2 does not accept the costs
+    pub fn new(algorithm: Algorithm, memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, String> {
+        let params = Params::new(memory_kib, iterations, parallelism, None).map_err(|e| e.to_string())?;
+        Ok(HashParams { algorithm, params })
+    }
 
-    let password_hash = argon2
-        .hash_password(password.as_bytes(), &salt)
-        .expect("Failed to hash password")
+    pub fn memory_kib(&self) -> u32 {
+        self.params.m_cost()
+    }
+
+    pub fn iterations(&self) -> u32 {
+        self.params.t_cost()
+    }
+
+    pub fn parallelism(&self) -> u32 {
+        self.params.p_cost()
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(self.algorithm, Version::V0x13, self.params.clone())
+    }
+}
+
+// Stored hashes are checked by the server, so the only failure left on the
+// client is making a new one
+#[derive(Debug)]
+pub enum HashError {
+    // Argon2 could not hash the password, e.g. not enough memory for the costs
+    Hashing(password_hash::Error),
+}
+
+impl fmt::Display for HashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashError::Hashing(e) => write!(f, "failed to hash password ({})", e),
+        }
+    }
+}
+
+impl std::error::Error for HashError {}
+
+// Every Argon2 call the client makes, account password hashes and the vault
+// key alike, runs here: on tokio's blocking threads so the async executor is
+// never stalled, and at most one per core at a time since each call
+// allocates its whole memory cost.
+pub async fn run_on_pool<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    static PERMITS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+    let permits = PERMITS.get_or_init(|| {
+        Arc::new(Semaphore::new(std::thread::available_parallelism().map_or(1, |n| n.get())))
+    });
+
+    let permit = permits
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("the hashing pool is never closed");
+    let task = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        f()
+    });
+    match task.await {
+        Ok(value) => value,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+pub async fn hash_password(password: &SecretString, params: &HashParams) -> Result<String, HashError> {
+    let (password, params) = (password.clone(), params.clone());
+    run_on_pool(move || hash_password_blocking(&password, &params)).await
+}
+
+fn hash_password_blocking(password: &SecretString, params: &HashParams) -> Result<String, HashError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    let password_hash = params
+        .argon2()
+        .hash_password(password.expose().as_bytes(), &salt)
+        .map_err(HashError::Hashing)?
         .to_string();
 
-    password_hash
+    Ok(password_hash)
+}
+
+// True when the hash was made with a different variant, version or costs than
+// the configured ones, so it should be replaced after the next successful
+// verification. Takes a whole hash or just its parameters as returned by the
+// server's verify endpoint. Values that cannot be parsed are left alone.
+pub fn needs_rehash(hash: &str, params: &HashParams) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(hash) else {
+        return false;
+    };
+    let Ok(stored) = Params::try_from(&parsed_hash) else {
+        return false;
+    };
+
+    parsed_hash.algorithm != params.algorithm.ident()
+        || parsed_hash.version != Some(Version::V0x13.into())
+        || stored.m_cost() != params.memory_kib()
+        || stored.t_cost() != params.iterations()
+        || stored.p_cost() != params.parallelism()
 }
 
-pub fn verify_hashed_password(hash: &str, password: &str) -> bool {
-    let parsed_hash = PasswordHash::new(hash).expect("Failed to parse hash");
-    Argon2::default()
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .is_ok()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What the server's verify endpoint answers for a hash made with the
+    // defaults (password_api::auth::hash_parameters)
+    const DEFAULT_PARAMETERS: &str = "$argon2id$v=19$m=4096,t=3,p=1";
+
+    fn hash_with(params: &HashParams) -> String {
+        hash_password_blocking(&SecretString::from("Tq9#vLm2!xZr8pWe".to_string()), params).unwrap()
+    }
+
+    #[test]
+    fn current_hashes_are_kept() {
+        let params = HashParams::default();
+        assert!(!needs_rehash(&hash_with(&params), &params));
+        assert!(!needs_rehash(DEFAULT_PARAMETERS, &params));
+
+        let custom = HashParams::new(Algorithm::Argon2id, 8192, 1, 1).unwrap();
+        assert!(!needs_rehash(&hash_with(&custom), &custom));
+    }
+
+    #[test]
+    fn changed_costs_or_algorithm_need_a_rehash() {
+        let hash = hash_with(&HashParams::default());
+        let defaults = HashParams::default();
+        let more_memory = HashParams::new(Algorithm::Argon2id, defaults.memory_kib() * 2, defaults.iterations(), 1).unwrap();
+        let more_iterations = HashParams::new(Algorithm::Argon2id, defaults.memory_kib(), defaults.iterations() + 1, 1).unwrap();
+        let argon2i = HashParams::new(Algorithm::Argon2i, defaults.memory_kib(), defaults.iterations(), 1).unwrap();
+
+        for params in [&more_memory, &more_iterations, &argon2i] {
+            assert!(needs_rehash(&hash, params), "{:?}", params);
+            assert!(needs_rehash(DEFAULT_PARAMETERS, params), "{:?}", params);
+        }
+
+        // Weaker costs or the older Argon2 version
+        assert!(needs_rehash("$argon2id$v=19$m=1024,t=3,p=1", &defaults));
+        assert!(needs_rehash("$argon2id$v=16$m=4096,t=3,p=1", &defaults));
+    }
+
+    #[test]
+    fn unparseable_hashes_are_left_alone() {
+        let params = HashParams::default();
+        assert!(!needs_rehash("", &params));
+        assert!(!needs_rehash("plaintext password", &params));
+        assert!(!needs_rehash("$argon2id$v=19$m=lots,t=2,p=1", &params));
+    }
 }
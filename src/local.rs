@@ -0,0 +1,50 @@
+use hyper::Server;
+use password_api::store::{self, Backend, PoolConfig};
+use password_api::HashingPool;
+use std::net::{SocketAddr, TcpListener};
+
+pub const DEFAULT_DB_PATH: &str = "passwords.db";
+
+// Starts password_api inside this process, backed by a SQLite file and bound
+// to a free loopback port, and returns the base URL the client should use.
+pub async fn start(db_path: &str) -> Option<String> {
+    let backend = Backend::Sqlite(format!("sqlite://{}", db_path));
+    let store = match store::open(&backend, &PoolConfig::default()).await {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Failed to open local database {}: {}", db_path, e);
+            return None;
+        }
+    };
+
+    if let Err(e) = store.migrate().await {
+        println!("Failed to prepare local database {}: {}", db_path, e);
+        return None;
+    }
+
+    let listener = match TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))) {
+        Ok(l) => l,
+        Err(e) => {
+            println!("Failed to start local server: {}", e);
+            return None;
+        }
+    };
+    let addr = listener.local_addr().ok()?;
+
+    let server = match Server::from_tcp(listener) {
+        Ok(builder) => builder.serve(password_api::app(store, HashingPool::default(), Vec::new()).into_make_service()),
+        Err(e) => {
+            println!("Failed to start local server: {}", e);
+            return None;
+        }
+    };
+
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            eprintln!("Local server stopped: {}", e);
+        }
+    });
+
+    eprintln!("Using local database {}", db_path);
+    Some(format!("http://{}", addr))
+}
@@ -1,143 +1,293 @@
-use axum::{ // Axum is only used on server side, but keep reqwest for client
-    extract::{Path, State},
-    routing::{delete, get, post},
-    Json, Router,
+use password_client::{ClientOptions, PasswordApiClient};
+use password_common::{
+    generate_passphrase, generate_password, generator, passphrase, AddEntry, Entry, PassphrasePolicy, PasswordPolicy,
+    UpdateEntry, VerifyResult, Wordlist,
 };
-use dotenvy::dotenv;
-use hyper::Server;
-use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
-use reqwest::Client;
-use rpassword::read_password;
+use std::process::ExitCode;
+use clap::Parser;
+#[allow(non_snake_case)]
 mod hashPassword;
-use hashPassword::verify_hashed_password;
+mod encryption;
+mod vault;
+use vault::Vault;
+mod local;
+mod cli;
+mod config;
+mod session;
+mod strength;
+mod breach;
+mod secret;
+use config::Profile;
+use secret::{read_hidden, SecretString};
+use strength::Verdict;
 
-#[derive(Debug, Deserialize)]
-struct ApiResponse<T> {
-    message: String,
-    http_code: u16,
-    data: Option<T>,
-}
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = cli::Cli::parse();
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AddEntry {
-    owner: String,
-    name: String,
-    username: String,
-    password: String,
-}
+    let mut profile = match config::load_profile(args.profile.as_deref()) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(cli::EXIT_FAILURE);
+        }
+    };
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Entry {
-    account_owner: Option<String>,
-    account_name: Option<String>,
-    account_username: Option<String>,
-    account_password: Option<String>,
-}
+    // --local runs password_api inside this process instead of using the profile's server
+    if args.local {
+        match local::start(&args.local_db).await {
+            Some(url) => profile.server_url = url,
+            None => return ExitCode::from(cli::EXIT_FAILURE),
+        }
+    }
+    let base_url = profile.server_url.clone();
+    let options = ClientOptions {
+        tls: profile.tls.clone(),
+        ..ClientOptions::default()
+    };
+    let mut client = match PasswordApiClient::with_options(&base_url, &options) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Failed to create HTTP client: {}", e);
+            return ExitCode::from(cli::EXIT_FAILURE);
+        }
+    };
 
-#[tokio::main]
-async fn main() {
-    entry_message().await;
-    begin_session().await;
+    if let Some(command) = args.command {
+        return cli::run(command, &mut client, &profile).await;
+    }
+
+    let owner = entry_message(&profile).await;
+    if let Some(vault) = vault::unlock(&mut client, &owner, profile.token_file.as_deref(), &profile.hashing).await {
+        begin_session(&client, &vault, &profile).await;
+    }
     exit_message().await;
+    ExitCode::SUCCESS
 }
 
 // ---------------- View entries ----------------
-pub async fn view_existing_entries(client: &Client) {
-    println!("\n\nPlease type in the account owner's name to see their records.");
-    let owner = read_input();
-
-    let url = format!("http://127.0.0.1:3000/entries/{}", owner);
-    let resp = client.get(&url).send().await;
-
-    match resp {
-        Ok(response) => {
-            let status = response.status();
-            let body_text = response.text().await.unwrap_or_default();
-
-            match serde_json::from_str::<ApiResponse<Vec<Entry>>>(&body_text) {
-                Ok(api_resp) => {
-                    println!("Server [{}]: {}", api_resp.http_code, api_resp.message);
-                    if let Some(entries) = api_resp.data {
-                        display_entries(&entries);
-
-                        let sel_trim = read_input();
-                        if sel_trim.is_empty() {
-                            println!("Skipped.");
-                            return;
-                        }
-
-                        if sel_trim.starts_with('d') {
-                            handle_delete(sel_trim, &entries, client).await;
-                        } else {
-                            handle_verification(sel_trim, &entries).await;
-                        }
-                    } else {
-                        println!("No entries found.");
-                    }
-                }
-                Err(e) => {
-                    println!("Failed to parse JSON: {}", e);
-                    println!("Raw response [{}]: {}", status.as_u16(), body_text);
-                }
-            }
+pub async fn view_existing_entries(client: &PasswordApiClient, vault: &Vault, profile: &Profile) {
+    let entries = match client.list_entries(&vault.owner).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("{}", e);
+            return;
         }
-        Err(e) => println!("Failed to contact server: {}", e),
+    };
+
+    display_entries(&entries);
+    if entries.is_empty() {
+        return;
+    }
+
+    let sel_trim = read_input();
+    if sel_trim.is_empty() {
+        println!("Skipped.");
+        return;
+    }
+
+    if sel_trim.starts_with('d') {
+        handle_delete(sel_trim, &entries, client, vault, profile).await;
+    } else if sel_trim.starts_with('e') {
+        handle_edit(sel_trim, &entries, client, vault, profile).await;
+    } else if sel_trim.starts_with('r') {
+        handle_reveal(sel_trim, &entries, vault).await;
+    } else {
+        handle_verification(sel_trim, &entries, client, vault, profile).await;
     }
 }
 
 // ---------------- Add entry ----------------
-async fn add_new_entry(client: &Client) {
+async fn add_new_entry(client: &PasswordApiClient, vault: &Vault, profile: &Profile) {
     println!("\nPlease enter the data for the new entry.");
 
-    fn prompt(msg: &str) -> String {
-        print!("{}", msg);
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Failed to read line");
-        input.trim().to_string()
-    }
-
-    let account_owner = prompt("Enter account owner: ");
     let account_name = prompt("Enter account name: ");
     let account_username = prompt("Enter account username: ");
-    let account_password = prompt("Enter account password: ");
+    let choice = prompt("Generate a random password (y), a passphrase (p), or type your own (n)? [Y/p/n]: ");
+    let generated = match choice.to_lowercase().as_str() {
+        "" | "y" | "yes" => generate_for_entry(),
+        "p" => passphrase_for_entry(&profile.wordlist),
+        _ => read_new_password("Enter account password (hidden): ", false),
+    };
+    let account_password = match generated {
+        Some(p) => p,
+        None => {
+            println!("Entry not added.");
+            return;
+        }
+    };
+    if !password_acceptable(&account_password, &[&vault.owner, &account_name, &account_username], profile) {
+        println!("Entry not added.");
+        return;
+    }
 
-    let hashed_password = hashPassword::hash_password(&account_password);
+    let encrypted = vault.encrypt(&account_password);
+    let hashed_password = match hashPassword::hash_password(&account_password, &profile.hashing).await {
+        Ok(h) => h,
+        Err(e) => {
+            println!("{}. Entry not added.", e);
+            return;
+        }
+    };
     let new_entry = AddEntry {
-        owner: account_owner,
+        owner: vault.owner.clone(),
         name: account_name,
         username: account_username,
         password: hashed_password,
+        ciphertext: encrypted.ciphertext,
+        nonce: encrypted.nonce,
+    };
+
+    match client.add_entry(&new_entry).await {
+        Ok(entry) => println!("Entry added with id {}.", entry.id),
+        Err(e) => println!("{}", e),
+    }
+}
+
+// Shows the strength estimate and any breach count and applies the profile's
+// policy; true when the password may be saved
+fn password_acceptable(password: &SecretString, user_inputs: &[&str], profile: &Profile) -> bool {
+    let assessment = strength::assess(
+        password.expose(),
+        user_inputs,
+        &profile.strength,
+        profile.breach_list.as_ref(),
+    );
+    println!("{}", assessment.estimate);
+    report_breaches(&assessment.breaches);
+
+    match assessment.verdict {
+        Verdict::Accept => true,
+        Verdict::Refuse(shortfall) => {
+            println!("This password {}, so it cannot be saved.", shortfall);
+            false
+        }
+        Verdict::Warn(shortfall) => {
+            let answer = prompt(&format!("This password {}. Save it anyway? [y/N]: ", shortfall));
+            matches!(answer.to_lowercase().as_str(), "y" | "yes")
+        }
+    }
+}
+
+// Prints the result of a breached password lookup, if there was one; true
+// for a hit
+fn report_breaches(breaches: &Option<io::Result<u64>>) -> bool {
+    match breaches {
+        None => false,
+        Some(Ok(0)) => {
+            println!("Not found in the breached password list.");
+            false
+        }
+        Some(Ok(count)) => {
+            println!("Warning: this password has appeared {} times in known data breaches.", count);
+            true
+        }
+        Some(Err(e)) => {
+            println!("Could not check the breached password list: {}", e);
+            false
+        }
+    }
+}
+
+// Reads a new password twice without echoing it; None if the two differ.
+// With allow_empty, pressing Enter returns an empty password without asking
+// for confirmation, for callers where that means keeping the current one.
+fn read_new_password(message: &str, allow_empty: bool) -> Option<SecretString> {
+    println!("{}", message);
+    let password = match read_hidden() {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Failed to read password: {}", e);
+            return None;
+        }
     };
+    if password.is_empty() {
+        if allow_empty {
+            return Some(password);
+        }
+        println!("The password cannot be empty.");
+        return None;
+    }
 
-    let resp = client
-        .post("http://127.0.0.1:3000/add")
-        .json(&new_entry)
-        .send()
-        .await;
+    println!("Confirm the password: ");
+    match read_hidden() {
+        Ok(confirmation) if confirmation == password => Some(password),
+        Ok(_) => {
+            println!("Passwords do not match.");
+            None
+        }
+        Err(e) => {
+            println!("Failed to read password: {}", e);
+            None
+        }
+    }
+}
 
-    match resp {
-        Ok(r) => {
-            let status = r.status();
-            let body_text = r.text().await.unwrap_or_default();
+// A number typed at a prompt, or the default when Enter is pressed
+fn prompt_count(label: &str, default: usize) -> Option<usize> {
+    let input = prompt(&format!("{} [{}]: ", label, default));
+    if input.is_empty() {
+        return Some(default);
+    }
+    match input.parse() {
+        Ok(n) => Some(n),
+        Err(_) => {
+            println!("Invalid number.");
+            None
+        }
+    }
+}
 
-            match serde_json::from_str::<ApiResponse<AddEntry>>(&body_text) {
-                Ok(api_resp) => {
-                    println!("Server [{}]: {}", api_resp.http_code, api_resp.message);
-                }
-                Err(e) => {
-                    println!("Failed to parse API response: {}", e);
-                    println!("Raw response [{}]: {}", status.as_u16(), body_text);
-                }
-            }
+fn generate_for_entry() -> Option<SecretString> {
+    let length = prompt_count("Password length", generator::DEFAULT_LENGTH)?;
+
+    let policy = PasswordPolicy {
+        length,
+        ..PasswordPolicy::default()
+    };
+    match generate_password(&policy) {
+        Ok(password) => {
+            println!("Generated password: {}", password);
+            println!("It is saved with the entry and can be shown again with r<number>.");
+            Some(SecretString::from(password))
+        }
+        Err(e) => {
+            println!("Cannot generate a password: {}", e);
+            None
+        }
+    }
+}
+
+fn passphrase_for_entry(wordlist: &Wordlist) -> Option<SecretString> {
+    let words = prompt_count("Number of words", passphrase::DEFAULT_WORDS)?;
+
+    let policy = PassphrasePolicy {
+        words,
+        ..PassphrasePolicy::default()
+    };
+    match generate_passphrase(&policy, wordlist) {
+        Ok(generated) => {
+            println!("Generated passphrase: {}", generated.passphrase);
+            println!("Entropy: {:.1} bits", generated.entropy_bits);
+            println!("It is saved with the entry and can be shown again with r<number>.");
+            Some(SecretString::from(generated.passphrase))
+        }
+        Err(e) => {
+            println!("Cannot generate a passphrase: {}", e);
+            None
         }
-        Err(e) => println!("Failed to contact server: {}", e),
     }
 }
 
 // ---------------- Delete entry ----------------
-async fn handle_delete(sel_trim: String, entries: &[Entry], client: &Client) {
+async fn handle_delete(
+    sel_trim: String,
+    entries: &[Entry],
+    client: &PasswordApiClient,
+    vault: &Vault,
+    profile: &Profile,
+) {
     let num_str = &sel_trim[1..];
     let sel_idx: usize = match num_str.parse::<usize>() {
         Ok(n) if n >= 1 && n <= entries.len() => n - 1,
@@ -148,16 +298,45 @@ async fn handle_delete(sel_trim: String, entries: &[Entry], client: &Client) {
     };
 
     let selected = &entries[sel_idx];
-    let stored_hash = match &selected.account_password {
-        Some(h) if !h.is_empty() => h,
+    println!("Enter the password to confirm deletion: ");
+    let assumed = match read_hidden() {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Failed to read password: {}", e);
+            return;
+        }
+    };
+
+    match check_with_server(client, vault, selected, &assumed, profile).await {
+        Some(result) if result.matches => match client.delete_entry(selected.id).await {
+            Ok(()) => println!("Entry deleted."),
+            Err(e) => println!("{}", e),
+        },
+        Some(_) => println!("Incorrect password. Entry not deleted."),
+        None => {}
+    }
+}
+
+// ---------------- Edit entry ----------------
+async fn handle_edit(
+    sel_trim: String,
+    entries: &[Entry],
+    client: &PasswordApiClient,
+    vault: &Vault,
+    profile: &Profile,
+) {
+    let num_str = &sel_trim[1..];
+    let sel_idx: usize = match num_str.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= entries.len() => n - 1,
         _ => {
-            println!("Selected entry has no stored password hash.");
+            println!("Invalid selection.");
             return;
         }
     };
 
-    println!("Enter the password to confirm deletion: ");
-    let assumed = match read_password() {
+    let selected = &entries[sel_idx];
+    println!("Enter the current password to confirm editing: ");
+    let assumed = match read_hidden() {
         Ok(s) => s,
         Err(e) => {
             println!("Failed to read password: {}", e);
@@ -165,36 +344,76 @@ async fn handle_delete(sel_trim: String, entries: &[Entry], client: &Client) {
         }
     };
 
-    if verify_hashed_password(stored_hash, &assumed) {
-        let owner = selected.account_owner.clone().unwrap_or_default();
-        let name = selected.account_name.clone().unwrap_or_default();
-        let url = format!("http://127.0.0.1:3000/delete/{}/{}", owner, name);
-
-        let resp = client.delete(&url).send().await;
-        match resp {
-            Ok(r) => {
-                let status = r.status();
-                let body_text = r.text().await.unwrap_or_default();
-
-                match serde_json::from_str::<ApiResponse<()>>(&body_text) {
-                    Ok(api_resp) => {
-                        println!("Server [{}]: {}", api_resp.http_code, api_resp.message);
-                    }
-                    Err(e) => {
-                        println!("Failed to parse server response: {}", e);
-                        println!("Raw response [{}]: {}", status.as_u16(), body_text);
-                    }
-                }
-            }
-            Err(e) => println!("Failed to contact server: {}", e),
+    let verified = match check_with_server(client, vault, selected, &assumed, profile).await {
+        Some(result) if result.matches => result,
+        Some(_) => {
+            println!("Incorrect password. Entry not changed.");
+            return;
         }
+        None => return,
+    };
+
+    println!("\nEnter the new values, or press Enter to keep the current one.");
+    let current_name = selected.account_name.clone().unwrap_or_default();
+    let new_name = prompt(&format!("Account name [{}]: ", current_name));
+    let new_username = prompt(&format!(
+        "Account username [{}]: ",
+        selected.account_username.clone().unwrap_or_default()
+    ));
+
+    let Some(new_password) = read_new_password("New account password (hidden, Enter to keep): ", true) else {
+        println!("Entry not changed.");
+        return;
+    };
+
+    if !new_password.is_empty() {
+        let user_inputs = [vault.owner.as_str(), &current_name, &new_name, &new_username];
+        if !password_acceptable(&new_password, &user_inputs, profile) {
+            println!("Entry not changed.");
+            return;
+        }
+    }
+
+    let mut update = UpdateEntry {
+        name: Some(new_name).filter(|s| !s.is_empty()),
+        username: Some(new_username).filter(|s| !s.is_empty()),
+        ..UpdateEntry::default()
+    };
+    if !new_password.is_empty() {
+        let hashed_password = match hashPassword::hash_password(&new_password, &profile.hashing).await {
+            Ok(h) => h,
+            Err(e) => {
+                println!("{}. Entry not changed.", e);
+                return;
+            }
+        };
+        let encrypted = vault.encrypt(&new_password);
+        update.password = Some(hashed_password);
+        update.ciphertext = Some(encrypted.ciphertext);
+        update.nonce = Some(encrypted.nonce);
     } else {
-        println!("Incorrect password. Entry not deleted.");
+        upgrade_hash(client, vault, selected, &verified, &assumed, profile).await;
+    }
+
+    if update.name.is_none() && update.username.is_none() && update.password.is_none() {
+        println!("Nothing to update.");
+        return;
+    }
+
+    match client.update_entry(selected.id, &update).await {
+        Ok(()) => println!("Entry updated."),
+        Err(e) => println!("{}", e),
     }
 }
 
 // ---------------- Verify password ----------------
-async fn handle_verification(sel_trim: String, entries: &[Entry]) {
+async fn handle_verification(
+    sel_trim: String,
+    entries: &[Entry],
+    client: &PasswordApiClient,
+    vault: &Vault,
+    profile: &Profile,
+) {
     let sel_idx: usize = match sel_trim.parse::<usize>() {
         Ok(n) if n >= 1 && n <= entries.len() => n - 1,
         _ => {
@@ -204,27 +423,138 @@ async fn handle_verification(sel_trim: String, entries: &[Entry]) {
     };
 
     let selected = &entries[sel_idx];
-    let stored_hash = match &selected.account_password {
-        Some(h) if !h.is_empty() => h,
-        _ => {
-            println!("Selected entry has no stored password hash.");
+    println!("Enter the password to check: ");
+    let assumed = match read_hidden() {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Failed to read password: {}", e);
             return;
         }
     };
 
-    println!("Enter the password to check: ");
-    let assumed = match read_password() {
-        Ok(s) => s,
+    match check_with_server(client, vault, selected, &assumed, profile).await {
+        Some(result) if result.matches => {
+            println!("Correct password.");
+            upgrade_hash(client, vault, selected, &result, &assumed, profile).await;
+            let breaches = profile.breach_list.as_ref().map(|list| list.count(assumed.expose()));
+            if report_breaches(&breaches) {
+                println!("Consider changing it with e{}.", sel_idx + 1);
+            }
+        }
+        Some(_) => println!("Incorrect password."),
+        None => {}
+    }
+}
+
+// The server checks the password so the stored hash never leaves it. None
+// after reporting why it could not tell; for a missing or damaged hash,
+// repair or removal is offered.
+async fn check_with_server(
+    client: &PasswordApiClient,
+    vault: &Vault,
+    entry: &Entry,
+    password: &SecretString,
+    profile: &Profile,
+) -> Option<VerifyResult> {
+    match client.verify_entry(entry.id, password).await {
+        Ok(result) => Some(result),
+        Err(e) if e.is_unprocessable() => {
+            println!("Cannot check this entry. {}", e);
+            offer_repair(client, vault, entry, profile).await;
+            None
+        }
         Err(e) => {
-            println!("Failed to read password: {}", e);
+            println!("{}", e);
+            None
+        }
+    }
+}
+
+// Replaces the entry's hash if it was made with older parameters than the profile's
+async fn upgrade_hash(
+    client: &PasswordApiClient,
+    vault: &Vault,
+    entry: &Entry,
+    verified: &VerifyResult,
+    password: &SecretString,
+    profile: &Profile,
+) {
+    let stored = verified.hash_parameters.as_deref();
+    match vault::upgrade_hash(client, vault, entry.id, stored, password, &profile.hashing).await {
+        Ok(true) => println!("Upgraded the stored hash to the current parameters."),
+        Ok(false) => {}
+        Err(e) => println!("Could not upgrade the stored hash: {}", e),
+    }
+}
+
+// ---------------- Repair entry ----------------
+// Offered when an entry's stored hash is missing or unusable, instead of
+// leaving a record that can never be verified
+async fn offer_repair(client: &PasswordApiClient, vault: &Vault, entry: &Entry, profile: &Profile) {
+    let choice = prompt("Repair it with a fresh hash (r), remove it (d), or leave it as it is (Enter)? ");
+    match choice.to_lowercase().as_str() {
+        "r" => repair_entry(client, vault, entry, profile).await,
+        "d" => match client.delete_entry(entry.id).await {
+            Ok(()) => println!("Entry deleted."),
+            Err(e) => println!("{}", e),
+        },
+        _ => println!("Entry left unchanged."),
+    }
+}
+
+// The encrypted copy usually survives a damaged hash; otherwise the password
+// has to be typed in again
+async fn repair_entry(client: &PasswordApiClient, vault: &Vault, entry: &Entry, profile: &Profile) {
+    let recovered = match (&entry.account_ciphertext, &entry.account_nonce) {
+        (Some(ciphertext), Some(nonce)) => vault.decrypt(ciphertext, nonce),
+        _ => None,
+    };
+    let password = match recovered {
+        Some(p) => {
+            println!("Recovered the password from its encrypted copy.");
+            p
+        }
+        None => {
+            println!("The encrypted copy cannot be read either.");
+            match read_new_password("Enter the account password to store again (hidden): ", false) {
+                Some(p) => p,
+                None => {
+                    println!("Entry not repaired.");
+                    return;
+                }
+            }
+        }
+    };
+
+    match vault::store_password(client, vault, entry.id, &password, &profile.hashing).await {
+        Ok(()) => println!("Entry repaired."),
+        Err(e) => println!("Could not repair the entry: {}", e),
+    }
+}
+
+// ---------------- Reveal password ----------------
+async fn handle_reveal(sel_trim: String, entries: &[Entry], vault: &Vault) {
+    let num_str = &sel_trim[1..];
+    let sel_idx: usize = match num_str.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= entries.len() => n - 1,
+        _ => {
+            println!("Invalid selection.");
             return;
         }
     };
 
-    if verify_hashed_password(stored_hash, &assumed) {
-        println!("Correct password.");
-    } else {
-        println!("Incorrect password.");
+    let selected = &entries[sel_idx];
+    let (ciphertext, nonce) = match (&selected.account_ciphertext, &selected.account_nonce) {
+        (Some(c), Some(n)) if !c.is_empty() && !n.is_empty() => (c, n),
+        _ => {
+            println!("Selected entry has no encrypted password stored.");
+            return;
+        }
+    };
+
+    match vault.decrypt(ciphertext, nonce) {
+        Some(password) => println!("Password: {}", password.expose()),
+        None => println!("Could not decrypt password. The stored data may be corrupted."),
     }
 }
 
@@ -237,6 +567,12 @@ fn read_input() -> String {
     input.trim().to_string()
 }
 
+fn prompt(msg: &str) -> String {
+    print!("{}", msg);
+    io::stdout().flush().unwrap();
+    read_input()
+}
+
 fn display_entries(entries: &[Entry]) {
     if entries.is_empty() {
         println!("No entries found.");
@@ -247,23 +583,27 @@ fn display_entries(entries: &[Entry]) {
     for (i, entry) in entries.iter().enumerate() {
         println!("{}. Account: {}", i + 1, entry.account_name.clone().unwrap_or_default());
         println!("   Username: {}", entry.account_username.clone().unwrap_or_default());
-        println!("   Password (hashed): {}", entry.account_password.clone().unwrap_or_default());
+        println!(
+            "   Created: {}  Last updated: {}",
+            entry.created_at.format("%Y-%m-%d %H:%M:%S"),
+            entry.updated_at.format("%Y-%m-%d %H:%M:%S")
+        );
     }
 
     println!("\nOptions:");
     println!("  Enter a number to check its password");
+    println!("  Enter e<number> to edit that entry (e.g., e2)");
+    println!("  Enter r<number> to reveal that entry's password (e.g., r2)");
     println!("  Enter d<number> to delete that entry (e.g., d2)");
     println!("  Or just press Enter to skip:");
 }
 
-async fn begin_session() {
-    let client = reqwest::Client::new();
-
+async fn begin_session(client: &PasswordApiClient, vault: &Vault, profile: &Profile) {
     loop {
         let decision = get_decision().await;
         match decision.as_str() {
-            "A" => view_existing_entries(&client).await,
-            "B" => add_new_entry(&client).await,
+            "A" => view_existing_entries(client, vault, profile).await,
+            "B" => add_new_entry(client, vault, profile).await,
             "C" => {
                 println!("\nExiting now.");
                 break;
@@ -287,15 +627,22 @@ C - Quit";
     input.trim().to_uppercase()
 }
 
-async fn entry_message() {
+async fn entry_message(profile: &Profile) -> String {
     println!("Hello! This is AP's Password Management Application!");
-    println!("Please enter your name:");
+    match &profile.default_owner {
+        Some(owner) => println!("Please enter your name [{}]:", owner),
+        None => println!("Please enter your name:"),
+    }
 
     let mut input = String::new();
     io::stdin().read_line(&mut input).expect("Failed to read line");
 
-    let name = input.trim();
+    let mut name = input.trim().to_string();
+    if name.is_empty() {
+        name = profile.default_owner.clone().unwrap_or_default();
+    }
     println!("Hello there {}!", name);
+    name
 }
 
 async fn exit_message() {
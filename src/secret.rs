@@ -0,0 +1,13 @@
+use std::io;
+
+// Lives in password_client so the API calls that carry secrets can take it
+pub use password_client::SecretString;
+
+// rpassword's hidden terminal input, kept in a SecretString
+pub fn read_hidden() -> io::Result<SecretString> {
+    rpassword::read_password().map(SecretString::from)
+}
+
+pub fn prompt_hidden(prompt: &str) -> io::Result<SecretString> {
+    rpassword::prompt_password(prompt).map(SecretString::from)
+}
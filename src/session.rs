@@ -0,0 +1,62 @@
+use chrono::{Duration, Utc};
+use password_common::LoginResponse;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+// Tokens this close to expiring are not reused
+const EXPIRY_MARGIN_SECONDS: i64 = 60;
+
+// A server session kept in a profile's token_file between runs
+#[derive(Serialize, Deserialize)]
+struct SavedSession {
+    server_url: String,
+    owner: String,
+    token: String,
+    expires_at: i64,
+}
+
+// The saved token, if it was issued by this server for this owner and is still valid
+pub fn load(path: &Path, server_url: &str, owner: &str) -> Option<String> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let saved: SavedSession = serde_json::from_str(&text).ok()?;
+
+    let still_valid = saved.expires_at - EXPIRY_MARGIN_SECONDS > Utc::now().timestamp();
+    if saved.server_url == server_url && saved.owner == owner && still_valid {
+        Some(saved.token)
+    } else {
+        None
+    }
+}
+
+// Failing to save only means logging in again next time, so errors are reported and ignored
+pub fn save(path: &Path, server_url: &str, owner: &str, login: &LoginResponse) {
+    let saved = SavedSession {
+        server_url: server_url.to_string(),
+        owner: owner.to_string(),
+        token: login.token.clone(),
+        expires_at: (Utc::now() + Duration::seconds(login.expires_in as i64)).timestamp(),
+    };
+
+    if let Err(e) = write_private(path, &serde_json::to_string(&saved).unwrap_or_default()) {
+        eprintln!("Failed to save session token to {}: {}", path.display(), e);
+    }
+}
+
+// The token grants access to the owner's records, so only the user may read the file
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(contents.as_bytes())
+}
@@ -0,0 +1,1182 @@
+// Password strength estimation in the manner of zxcvbn: the password is split
+// into the patterns an attacker would try (common passwords, words, keyboard
+// walks, sequences, repeats, dates), and the cheapest way to cover it gives
+// the estimated number of guesses.
+use chrono::Datelike;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::sync::OnceLock;
+use zeroize::Zeroize;
+
+use crate::breach::BreachList;
+
+// zxcvbn's ranked lists, most common first (see wordlists/LICENSE-zxcvbn)
+const COMMON_PASSWORDS: &str = include_str!("../wordlists/common_passwords.txt");
+const ENGLISH_WORDS: &str = include_str!("../wordlists/english_words.txt");
+const US_TV_AND_FILM: &str = include_str!("../wordlists/us_tv_and_film.txt");
+const FEMALE_NAMES: &str = include_str!("../wordlists/female_names.txt");
+const MALE_NAMES: &str = include_str!("../wordlists/male_names.txt");
+const SURNAMES: &str = include_str!("../wordlists/surnames.txt");
+
+pub const MAX_SCORE: u8 = 4;
+pub const DEFAULT_MIN_SCORE: u8 = 3;
+
+// Only the start of a very long password is examined
+const MAX_LENGTH: usize = 100;
+const MIN_SUBMATCH_GUESSES_SINGLE_CHAR: f64 = 10.0;
+const MIN_SUBMATCH_GUESSES_MULTI_CHAR: f64 = 50.0;
+const MIN_YEAR_SPACE: i32 = 20;
+// Guesses per second against a stolen Argon2 hash, and against a login
+// form that limits attempts
+const OFFLINE_SLOW_HASH_RATE: f64 = 1e4;
+const ONLINE_THROTTLED_RATE: f64 = 100.0 / 3600.0;
+
+// ---------------- Policy ----------------
+
+// What happens to a password scoring below the minimum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Enforcement {
+    // Show the estimate and ask (or, in scripts, carry on)
+    #[default]
+    Warn,
+    // Do not save it
+    Refuse,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StrengthPolicy {
+    // 0 to 4, as in Estimate::score
+    pub min_score: u8,
+    pub enforcement: Enforcement,
+}
+
+impl Default for StrengthPolicy {
+    fn default() -> Self {
+        StrengthPolicy {
+            min_score: DEFAULT_MIN_SCORE,
+            enforcement: Enforcement::Warn,
+        }
+    }
+}
+
+impl StrengthPolicy {
+    pub fn accepts(&self, estimate: &Estimate) -> bool {
+        estimate.score >= self.min_score
+    }
+
+    // A breach counts against a password before its score does
+    pub fn verdict(&self, estimate: &Estimate, breaches: u64) -> Verdict {
+        let shortfall = if breaches > 0 {
+            Shortfall::Breached(breaches)
+        } else if !self.accepts(estimate) {
+            Shortfall::BelowMinimum(self.min_score)
+        } else {
+            return Verdict::Accept;
+        };
+        match self.enforcement {
+            Enforcement::Warn => Verdict::Warn(shortfall),
+            Enforcement::Refuse => Verdict::Refuse(shortfall),
+        }
+    }
+}
+
+// ---------------- Verdict ----------------
+
+// Why a new password falls short of the policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortfall {
+    Breached(u64),
+    BelowMinimum(u8),
+}
+
+// Reads after "This password" or "it"
+impl fmt::Display for Shortfall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shortfall::Breached(_) => write!(f, "has been exposed in a data breach"),
+            Shortfall::BelowMinimum(score) => write!(f, "is below the recommended score of {}", score),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    // May be saved once the user agrees; scripts save it with a warning
+    Warn(Shortfall),
+    Refuse(Shortfall),
+}
+
+pub struct Assessment {
+    pub estimate: Estimate,
+    // None when there is no breach list to look in
+    pub breaches: Option<io::Result<u64>>,
+    pub verdict: Verdict,
+}
+
+// Everything decided about a new password, so the interactive and scripted
+// clients only differ in how they show it. A failed breach lookup counts as
+// no breaches.
+pub fn assess(
+    password: &str,
+    user_inputs: &[&str],
+    policy: &StrengthPolicy,
+    breach_list: Option<&BreachList>,
+) -> Assessment {
+    let estimate = estimate(password, user_inputs);
+    let breaches = breach_list.map(|list| list.count(password));
+    let count = match &breaches {
+        Some(Ok(count)) => *count,
+        _ => 0,
+    };
+    let verdict = policy.verdict(&estimate, count);
+    Assessment {
+        estimate,
+        breaches,
+        verdict,
+    }
+}
+
+// ---------------- Estimate ----------------
+
+pub struct Estimate {
+    pub guesses: f64,
+    // 0 (too guessable) to 4 (very unguessable)
+    pub score: u8,
+    pub offline_crack_seconds: f64,
+    pub online_crack_seconds: f64,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = ["very weak", "weak", "fair", "strong", "very strong"][self.score as usize];
+        writeln!(f, "Strength: {}/{} ({})", self.score, MAX_SCORE, label)?;
+        write!(
+            f,
+            "Estimated time to crack: {} from a stolen hash, {} by trying logins",
+            display_time(self.offline_crack_seconds),
+            display_time(self.online_crack_seconds)
+        )?;
+        if let Some(warning) = &self.warning {
+            write!(f, "\nWarning: {}", warning)?;
+        }
+        for suggestion in &self.suggestions {
+            write!(f, "\nSuggestion: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+// user_inputs are words the password should not be built from, such as the
+// owner, account name and username
+pub fn estimate(password: &str, user_inputs: &[&str]) -> Estimate {
+    let mut chars: Vec<char> = password.chars().take(MAX_LENGTH).collect();
+
+    let mut user_dict = HashMap::new();
+    for (rank, input) in user_inputs.iter().enumerate() {
+        let input = input.trim().to_lowercase();
+        if !input.is_empty() {
+            user_dict.entry(input).or_insert(rank + 1);
+        }
+    }
+
+    let (guesses, sequence) = most_guessable(&chars, &user_dict);
+    let score = score_for(guesses);
+    let (warning, suggestions) = feedback(score, &sequence);
+    // The whole copy is wiped; the pieces looked up while matching are not
+    chars.zeroize();
+
+    Estimate {
+        guesses,
+        score,
+        offline_crack_seconds: guesses / OFFLINE_SLOW_HASH_RATE,
+        online_crack_seconds: guesses / ONLINE_THROTTLED_RATE,
+        warning,
+        suggestions,
+    }
+}
+
+fn score_for(guesses: f64) -> u8 {
+    // A small margin so a password right at a threshold gets the lower score
+    const DELTA: f64 = 5.0;
+    match guesses {
+        g if g < 1e3 + DELTA => 0,
+        g if g < 1e6 + DELTA => 1,
+        g if g < 1e8 + DELTA => 2,
+        g if g < 1e10 + DELTA => 3,
+        _ => 4,
+    }
+}
+
+pub fn display_time(seconds: f64) -> String {
+    const MINUTE: f64 = 60.0;
+    const HOUR: f64 = MINUTE * 60.0;
+    const DAY: f64 = HOUR * 24.0;
+    const MONTH: f64 = DAY * 31.0;
+    const YEAR: f64 = MONTH * 12.0;
+    const CENTURY: f64 = YEAR * 100.0;
+
+    let (amount, unit) = match seconds {
+        s if s < 1.0 => return "less than a second".to_string(),
+        s if s < MINUTE => (s, "second"),
+        s if s < HOUR => (s / MINUTE, "minute"),
+        s if s < DAY => (s / HOUR, "hour"),
+        s if s < MONTH => (s / DAY, "day"),
+        s if s < YEAR => (s / MONTH, "month"),
+        s if s < CENTURY => (s / YEAR, "year"),
+        _ => return "centuries".to_string(),
+    };
+    let amount = amount.round() as u64;
+    format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+// ---------------- Matching ----------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DictKind {
+    Passwords,
+    // Wikipedia, and US television and film
+    English,
+    Names,
+    UserInputs,
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Dictionary {
+        kind: DictKind,
+        rank: usize,
+        reversed: bool,
+        // (substitute, letter) pairs, such as ('@', 'a')
+        l33t: Vec<(char, char)>,
+    },
+    Spatial { turns: usize, shifted: usize },
+    Repeat { base_len: usize, base_guesses: f64, count: usize },
+    Sequence { ascending: bool },
+    Year { year: i32 },
+    Date { year: i32, separator: bool },
+    Bruteforce,
+}
+
+// Covers chars[i..=j] of the password
+#[derive(Debug, Clone)]
+struct Match {
+    i: usize,
+    j: usize,
+    token: Vec<char>,
+    pattern: Pattern,
+}
+
+// Each list keeps its own ranks, so a word found in several is costed by
+// the list where it is most common
+fn dictionaries() -> &'static [(DictKind, HashMap<String, usize>)] {
+    static DICTIONARIES: OnceLock<Vec<(DictKind, HashMap<String, usize>)>> = OnceLock::new();
+    DICTIONARIES.get_or_init(|| {
+        let ranked = |words: Vec<String>| {
+            let mut dict = HashMap::new();
+            for (rank, word) in words.into_iter().enumerate() {
+                dict.entry(word).or_insert(rank + 1);
+            }
+            dict
+        };
+
+        let lines = |text: &str| {
+            text.lines()
+                .map(|l| l.trim().to_lowercase())
+                .filter(|l| !l.is_empty())
+                .collect()
+        };
+
+        vec![
+            (DictKind::Passwords, ranked(lines(COMMON_PASSWORDS))),
+            (DictKind::English, ranked(lines(ENGLISH_WORDS))),
+            (DictKind::English, ranked(lines(US_TV_AND_FILM))),
+            (DictKind::Names, ranked(lines(FEMALE_NAMES))),
+            (DictKind::Names, ranked(lines(MALE_NAMES))),
+            (DictKind::Names, ranked(lines(SURNAMES))),
+        ]
+    })
+}
+
+fn all_matches(chars: &[char], user_dict: &HashMap<String, usize>) -> Vec<Match> {
+    let mut dicts: Vec<(DictKind, &HashMap<String, usize>)> =
+        dictionaries().iter().map(|(kind, dict)| (*kind, dict)).collect();
+    dicts.push((DictKind::UserInputs, user_dict));
+
+    let mut matches = Vec::new();
+    matches.extend(dictionary_matches(chars, &dicts));
+    matches.extend(reversed_dictionary_matches(chars, &dicts));
+    matches.extend(l33t_matches(chars, &dicts));
+    matches.extend(spatial_matches(chars));
+    matches.extend(repeat_matches(chars, user_dict));
+    matches.extend(sequence_matches(chars));
+    matches.extend(date_matches(chars));
+    matches
+}
+
+fn dictionary_matches(chars: &[char], dicts: &[(DictKind, &HashMap<String, usize>)]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for i in 0..chars.len() {
+        for j in i..chars.len() {
+            let word: String = chars[i..=j].iter().collect::<String>().to_lowercase();
+            for (kind, dict) in dicts {
+                if let Some(&rank) = dict.get(&word) {
+                    matches.push(Match {
+                        i,
+                        j,
+                        token: chars[i..=j].to_vec(),
+                        pattern: Pattern::Dictionary {
+                            kind: *kind,
+                            rank,
+                            reversed: false,
+                            l33t: Vec::new(),
+                        },
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn reversed_dictionary_matches(chars: &[char], dicts: &[(DictKind, &HashMap<String, usize>)]) -> Vec<Match> {
+    let n = chars.len();
+    let mut reversed: Vec<char> = chars.iter().rev().copied().collect();
+    let matches = dictionary_matches(&reversed, dicts);
+    reversed.zeroize();
+
+    matches
+        .into_iter()
+        // A palindrome is already found the right way round
+        .filter(|m| m.token.iter().rev().ne(m.token.iter()))
+        .map(|m| {
+            let (i, j) = (n - 1 - m.j, n - 1 - m.i);
+            let pattern = match m.pattern {
+                Pattern::Dictionary { kind, rank, .. } => Pattern::Dictionary {
+                    kind,
+                    rank,
+                    reversed: true,
+                    l33t: Vec::new(),
+                },
+                other => other,
+            };
+            Match {
+                i,
+                j,
+                token: chars[i..=j].to_vec(),
+                pattern,
+            }
+        })
+        .collect()
+}
+
+const L33T_TABLE: &[(char, &[char])] = &[
+    ('a', &['4', '@']),
+    ('b', &['8']),
+    ('c', &['(', '{', '[', '<']),
+    ('e', &['3']),
+    ('g', &['6', '9']),
+    ('i', &['1', '!', '|']),
+    ('l', &['1', '|', '7']),
+    ('o', &['0']),
+    ('s', &['$', '5']),
+    ('t', &['+', '7']),
+    ('x', &['%']),
+    ('z', &['2']),
+];
+
+fn l33t_letters(sub: char) -> Vec<char> {
+    L33T_TABLE
+        .iter()
+        .filter(|(_, subs)| subs.contains(&sub))
+        .map(|(letter, _)| *letter)
+        .collect()
+}
+
+// Every way of reading the password's substitute characters as letters
+fn l33t_substitutions(chars: &[char]) -> Vec<Vec<(char, char)>> {
+    let mut subs: Vec<char> = chars.iter().copied().filter(|c| !l33t_letters(*c).is_empty()).collect();
+    subs.sort_unstable();
+    subs.dedup();
+
+    let mut tables: Vec<Vec<(char, char)>> = vec![Vec::new()];
+    for sub in subs {
+        let mut next = Vec::new();
+        for table in &tables {
+            for letter in l33t_letters(sub) {
+                let mut table = table.clone();
+                table.push((sub, letter));
+                next.push(table);
+            }
+        }
+        tables = next;
+        // Plenty for real passwords; stops pathological ones blowing up
+        tables.truncate(64);
+    }
+    tables.retain(|t| !t.is_empty());
+    tables
+}
+
+fn l33t_matches(chars: &[char], dicts: &[(DictKind, &HashMap<String, usize>)]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for table in l33t_substitutions(chars) {
+        let mut translated: Vec<char> = chars
+            .iter()
+            .map(|c| table.iter().find(|(sub, _)| sub == c).map_or(*c, |(_, letter)| *letter))
+            .collect();
+        let found = dictionary_matches(&translated, dicts);
+        translated.zeroize();
+
+        for m in found {
+            let token = chars[m.i..=m.j].to_vec();
+            let used: Vec<(char, char)> = table.iter().copied().filter(|(sub, _)| token.contains(sub)).collect();
+            // Single characters are better covered by brute force
+            if used.is_empty() || token.len() == 1 {
+                continue;
+            }
+            if let Pattern::Dictionary { kind, rank, .. } = m.pattern {
+                matches.push(Match {
+                    i: m.i,
+                    j: m.j,
+                    token,
+                    pattern: Pattern::Dictionary {
+                        kind,
+                        rank,
+                        reversed: false,
+                        l33t: used,
+                    },
+                });
+            }
+        }
+    }
+    matches
+}
+
+// US qwerty rows, unshifted and shifted
+const QWERTY: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+    ("asdfghjkl;'", "ASDFGHJKL:\""),
+    ("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+// How far the row above sits to the right of each row: q is below 1 and 2,
+// a below q and w, z below a and s
+const ROW_OFFSET: [usize; 4] = [0, 1, 0, 0];
+
+// (row, column, shifted)
+fn key_position(c: char) -> Option<(usize, usize, bool)> {
+    QWERTY.iter().enumerate().find_map(|(row, (plain, shifted))| {
+        if let Some(col) = plain.chars().position(|k| k == c) {
+            Some((row, col, false))
+        } else {
+            shifted.chars().position(|k| k == c).map(|col| (row, col, true))
+        }
+    })
+}
+
+fn row_len(row: usize) -> usize {
+    QWERTY[row].0.chars().count()
+}
+
+// Keys next to this one, each with the direction taken to reach it
+fn neighbours(row: usize, col: usize) -> Vec<((usize, usize), u8)> {
+    let mut keys = Vec::new();
+    if col > 0 {
+        keys.push(((row, col - 1), 0));
+    }
+    keys.push(((row, col + 1), 1));
+    if row > 0 {
+        let above = col + ROW_OFFSET[row];
+        keys.push(((row - 1, above), 2));
+        keys.push(((row - 1, above + 1), 3));
+    }
+    if row + 1 < QWERTY.len() {
+        let offset = ROW_OFFSET[row + 1];
+        if col > offset {
+            keys.push(((row + 1, col - offset - 1), 4));
+        }
+        if col >= offset {
+            keys.push(((row + 1, col - offset), 5));
+        }
+    }
+    keys.retain(|((r, c), _)| *c < row_len(*r));
+    keys
+}
+
+fn keyboard_stats() -> (f64, f64) {
+    let mut keys = 0usize;
+    let mut degrees = 0usize;
+    for row in 0..QWERTY.len() {
+        for col in 0..row_len(row) {
+            keys += 1;
+            degrees += neighbours(row, col).len();
+        }
+    }
+    // Shifted keys are starting positions too
+    ((keys * 2) as f64, degrees as f64 / keys as f64)
+}
+
+fn spatial_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + 2 < chars.len() {
+        let Some((mut row, mut col, shifted)) = key_position(chars[i]) else {
+            i += 1;
+            continue;
+        };
+        let mut shifted_count = usize::from(shifted);
+        let mut turns = 0;
+        let mut last_direction = None;
+        let mut j = i;
+
+        while j + 1 < chars.len() {
+            let Some((next_row, next_col, next_shifted)) = key_position(chars[j + 1]) else {
+                break;
+            };
+            let step = neighbours(row, col)
+                .into_iter()
+                .find(|(key, _)| *key == (next_row, next_col));
+            let Some((_, direction)) = step else {
+                break;
+            };
+            if last_direction != Some(direction) {
+                turns += 1;
+                last_direction = Some(direction);
+            }
+            shifted_count += usize::from(next_shifted);
+            row = next_row;
+            col = next_col;
+            j += 1;
+        }
+
+        if j - i + 1 >= 3 {
+            matches.push(Match {
+                i,
+                j,
+                token: chars[i..=j].to_vec(),
+                pattern: Pattern::Spatial {
+                    turns,
+                    shifted: shifted_count,
+                },
+            });
+        }
+        i = j.max(i + 1);
+    }
+    matches
+}
+
+fn repeat_matches(chars: &[char], user_dict: &HashMap<String, usize>) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        // The longest run of one repeated base starting here, shortest base first
+        let mut best: Option<(usize, usize)> = None;
+        for base_len in 1..=(chars.len() - i) / 2 {
+            let base = &chars[i..i + base_len];
+            let mut count = 1;
+            while i + (count + 1) * base_len <= chars.len()
+                && &chars[i + count * base_len..i + (count + 1) * base_len] == base
+            {
+                count += 1;
+            }
+            if count >= 2 && best.is_none_or(|(len, n)| count * base_len > len * n) {
+                best = Some((base_len, count));
+            }
+        }
+
+        match best {
+            Some((base_len, count)) => {
+                let j = i + base_len * count - 1;
+                let (base_guesses, _) = most_guessable(&chars[i..i + base_len], user_dict);
+                matches.push(Match {
+                    i,
+                    j,
+                    token: chars[i..=j].to_vec(),
+                    pattern: Pattern::Repeat {
+                        base_len,
+                        base_guesses,
+                        count,
+                    },
+                });
+                i = j + 1;
+            }
+            None => i += 1,
+        }
+    }
+    matches
+}
+
+fn sequence_matches(chars: &[char]) -> Vec<Match> {
+    const MAX_DELTA: i64 = 5;
+    let same_class = |token: &[char]| {
+        token.iter().all(|c| c.is_ascii_lowercase())
+            || token.iter().all(|c| c.is_ascii_uppercase())
+            || token.iter().all(|c| c.is_ascii_digit())
+    };
+
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + 1 < chars.len() {
+        let delta = chars[i + 1] as i64 - chars[i] as i64;
+        let mut j = i + 1;
+        while j + 1 < chars.len() && chars[j + 1] as i64 - chars[j] as i64 == delta {
+            j += 1;
+        }
+
+        let token = &chars[i..=j];
+        if token.len() >= 3 && delta != 0 && delta.abs() <= MAX_DELTA && same_class(token) {
+            matches.push(Match {
+                i,
+                j,
+                token: token.to_vec(),
+                pattern: Pattern::Sequence { ascending: delta > 0 },
+            });
+        }
+        i = j;
+    }
+    matches
+}
+
+fn reference_year() -> i32 {
+    chrono::Utc::now().year()
+}
+
+// Two digit years are taken as 19xx or 20xx, whichever is nearer
+fn expand_year(digits: &str) -> Option<i32> {
+    let year: i32 = digits.parse().ok()?;
+    match digits.len() {
+        2 if year > 50 => Some(1900 + year),
+        2 => Some(2000 + year),
+        4 if (1000..=2050).contains(&year) => Some(year),
+        _ => None,
+    }
+}
+
+// The year of the most plausible reading of day, month and year parts
+fn date_year(parts: &[&str; 3]) -> Option<i32> {
+    let readings = [
+        (parts[2], parts[0], parts[1]),
+        (parts[2], parts[1], parts[0]),
+        (parts[0], parts[1], parts[2]),
+        (parts[0], parts[2], parts[1]),
+    ];
+    readings
+        .iter()
+        .filter(|(_, a, b)| a.len() <= 2 && b.len() <= 2)
+        .filter_map(|(year, a, b)| {
+            let year = expand_year(year)?;
+            let a: u32 = a.parse().ok()?;
+            let b: u32 = b.parse().ok()?;
+            let valid = |day: u32, month: u32| (1..=31).contains(&day) && (1..=12).contains(&month);
+            (valid(a, b) || valid(b, a)).then_some(year)
+        })
+        .min_by_key(|year| (year - reference_year()).abs())
+}
+
+fn date_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+
+    for i in 0..chars.len() {
+        for j in i + 3..chars.len().min(i + 10) {
+            let token: String = chars[i..=j].iter().collect();
+
+            if token.len() == 4 && token.chars().all(|c| c.is_ascii_digit()) {
+                let year: i32 = token.parse().unwrap_or(0);
+                if (1900..=2099).contains(&year) {
+                    matches.push(Match {
+                        i,
+                        j,
+                        token: chars[i..=j].to_vec(),
+                        pattern: Pattern::Year { year },
+                    });
+                }
+            }
+
+            let found = if token.chars().all(|c| c.is_ascii_digit()) {
+                undelimited_date(&token).map(|year| (year, false))
+            } else {
+                delimited_date(&token).map(|year| (year, true))
+            };
+            if let Some((year, separator)) = found {
+                matches.push(Match {
+                    i,
+                    j,
+                    token: chars[i..=j].to_vec(),
+                    pattern: Pattern::Date { year, separator },
+                });
+            }
+        }
+    }
+    matches
+}
+
+// 4 to 8 digits, such as 1191 or 19910911
+fn undelimited_date(token: &str) -> Option<i32> {
+    if !(4..=8).contains(&token.len()) {
+        return None;
+    }
+    (1..token.len() - 1)
+        .flat_map(|a| (a + 1..token.len()).map(move |b| (a, b)))
+        .filter_map(|(a, b)| date_year(&[&token[..a], &token[a..b], &token[b..]]))
+        .min_by_key(|year| (year - reference_year()).abs())
+}
+
+// Three numbers split by the same separator, such as 9/11/91 or 1991-09-11
+fn delimited_date(token: &str) -> Option<i32> {
+    let separator = token.chars().find(|c| !c.is_ascii_digit())?;
+    if !" -/\\_.".contains(separator) {
+        return None;
+    }
+    let parts: Vec<&str> = token.split(separator).collect();
+    match parts.as_slice() {
+        [a, b, c] if parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())) => {
+            date_year(&[a, b, c])
+        }
+        _ => None,
+    }
+}
+
+// ---------------- Guess estimation ----------------
+
+fn n_choose_k(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (1..=k).fold(1.0, |acc, i| acc * (n - k + i) as f64 / i as f64)
+}
+
+fn factorial(n: usize) -> f64 {
+    (2..=n).fold(1.0, |acc, i| acc * i as f64)
+}
+
+// Ways to choose which of the characters take the other form: which letters
+// are capitals, which are substitutes
+fn variations(changed: usize, unchanged: usize) -> f64 {
+    if changed == 0 || unchanged == 0 {
+        return 2.0;
+    }
+    (1..=changed.min(unchanged)).map(|i| n_choose_k(changed + unchanged, i)).sum()
+}
+
+fn uppercase_variations(token: &[char]) -> f64 {
+    let upper = token.iter().filter(|c| c.is_uppercase()).count();
+    let lower = token.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 1.0;
+    }
+    // Capitalising the first or last letter, or all of them, is what people do
+    let first_upper = token.first().is_some_and(|c| c.is_uppercase()) && upper == 1;
+    let last_upper = token.last().is_some_and(|c| c.is_uppercase()) && upper == 1;
+    if first_upper || last_upper || lower == 0 {
+        return 2.0;
+    }
+    (1..=upper.min(lower)).map(|i| n_choose_k(upper + lower, i)).sum()
+}
+
+fn l33t_variations(token: &[char], l33t: &[(char, char)]) -> f64 {
+    let lower: Vec<char> = token.iter().flat_map(|c| c.to_lowercase()).collect();
+    l33t.iter()
+        .map(|(sub, letter)| {
+            let subbed = lower.iter().filter(|c| *c == sub).count();
+            let unsubbed = lower.iter().filter(|c| *c == letter).count();
+            variations(subbed, unsubbed)
+        })
+        .product()
+}
+
+fn spatial_guesses(length: usize, turns: usize, shifted: usize) -> f64 {
+    let (starts, degree) = keyboard_stats();
+    let mut guesses = 0.0;
+    for i in 2..=length {
+        for j in 1..=turns.min(i - 1) {
+            guesses += n_choose_k(i - 1, j - 1) * starts * degree.powi(j as i32);
+        }
+    }
+    if shifted > 0 {
+        guesses *= variations(shifted, length - shifted);
+    }
+    guesses
+}
+
+fn raw_guesses(m: &Match) -> f64 {
+    match &m.pattern {
+        Pattern::Dictionary {
+            rank, reversed, l33t, ..
+        } => {
+            let reversed = if *reversed { 2.0 } else { 1.0 };
+            *rank as f64 * uppercase_variations(&m.token) * l33t_variations(&m.token, l33t) * reversed
+        }
+        Pattern::Spatial { turns, shifted } => spatial_guesses(m.token.len(), *turns, *shifted),
+        Pattern::Repeat {
+            base_guesses, count, ..
+        } => base_guesses * *count as f64,
+        Pattern::Sequence { ascending } => {
+            let first = m.token[0];
+            let base = if "aAzZ019".contains(first) {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let direction = if *ascending { 1.0 } else { 2.0 };
+            base * direction * m.token.len() as f64
+        }
+        Pattern::Year { year } => (year - reference_year()).abs().max(MIN_YEAR_SPACE) as f64,
+        Pattern::Date { year, separator } => {
+            let days = (year - reference_year()).abs().max(MIN_YEAR_SPACE) as f64 * 365.0;
+            if *separator {
+                days * 4.0
+            } else {
+                days
+            }
+        }
+        Pattern::Bruteforce => {
+            let guesses = 10f64.powi(m.token.len() as i32);
+            let min = if m.token.len() == 1 {
+                MIN_SUBMATCH_GUESSES_SINGLE_CHAR
+            } else {
+                MIN_SUBMATCH_GUESSES_MULTI_CHAR
+            };
+            guesses.max(min + 1.0)
+        }
+    }
+}
+
+fn guesses(m: &Match, password_len: usize) -> f64 {
+    let min = if m.token.len() == password_len {
+        1.0
+    } else if m.token.len() == 1 {
+        MIN_SUBMATCH_GUESSES_SINGLE_CHAR
+    } else {
+        MIN_SUBMATCH_GUESSES_MULTI_CHAR
+    };
+    raw_guesses(m).max(min)
+}
+
+// Best way to cover chars[..=k] with a given number of matches
+struct Candidate {
+    last: Match,
+    // Product of the guesses of the matches
+    product: f64,
+    // Guesses for the whole prefix, allowing for the order of the matches
+    total: f64,
+}
+
+// The fewest guesses needed to find the password and the matches that give
+// them, searching over every way of covering it with matches and brute force
+fn most_guessable(chars: &[char], user_dict: &HashMap<String, usize>) -> (f64, Vec<Match>) {
+    let n = chars.len();
+    if n == 0 {
+        return (1.0, Vec::new());
+    }
+
+    let matches = all_matches(chars, user_dict);
+    let mut optimal: Vec<BTreeMap<usize, Candidate>> = (0..n).map(|_| BTreeMap::new()).collect();
+
+    let update = |optimal: &mut Vec<BTreeMap<usize, Candidate>>, m: Match, count: usize| {
+        let k = m.j;
+        let mut product = guesses(&m, n);
+        if count > 1 {
+            product *= optimal[m.i - 1][&(count - 1)].product;
+        }
+        // Extra matches cost an attacker an order of magnitude each
+        let total = factorial(count) * product + 10000f64.powi(count as i32 - 1);
+        let beaten = optimal[k]
+            .iter()
+            .any(|(&other_count, other)| other_count <= count && other.total <= total);
+        if !beaten {
+            optimal[k].insert(count, Candidate { last: m, product, total });
+        }
+    };
+    let bruteforce = |i: usize, j: usize| Match {
+        i,
+        j,
+        token: chars[i..=j].to_vec(),
+        pattern: Pattern::Bruteforce,
+    };
+
+    for k in 0..n {
+        for m in matches.iter().filter(|m| m.j == k) {
+            if m.i == 0 {
+                update(&mut optimal, m.clone(), 1);
+            } else {
+                let counts: Vec<usize> = optimal[m.i - 1].keys().copied().collect();
+                for count in counts {
+                    update(&mut optimal, m.clone(), count + 1);
+                }
+            }
+        }
+
+        update(&mut optimal, bruteforce(0, k), 1);
+        for i in 1..=k {
+            // Two brute force stretches in a row are one longer stretch
+            let counts: Vec<usize> = optimal[i - 1]
+                .iter()
+                .filter(|(_, c)| !matches!(c.last.pattern, Pattern::Bruteforce))
+                .map(|(&count, _)| count)
+                .collect();
+            for count in counts {
+                update(&mut optimal, bruteforce(i, k), count + 1);
+            }
+        }
+    }
+
+    let (mut count, best) = optimal[n - 1]
+        .iter()
+        .min_by(|a, b| a.1.total.total_cmp(&b.1.total))
+        .map(|(&count, c)| (count, c.total))
+        .expect("every prefix can be brute forced");
+
+    let mut sequence = Vec::new();
+    let mut k = n - 1;
+    loop {
+        let m = optimal[k][&count].last.clone();
+        let start = m.i;
+        sequence.push(m);
+        if start == 0 {
+            break;
+        }
+        k = start - 1;
+        count -= 1;
+    }
+    sequence.reverse();
+
+    (best, sequence)
+}
+
+// ---------------- Feedback ----------------
+
+fn feedback(score: u8, sequence: &[Match]) -> (Option<String>, Vec<String>) {
+    if sequence.is_empty() {
+        return (
+            None,
+            vec![
+                "Use a few words, avoid common phrases".to_string(),
+                "No need for symbols, digits, or uppercase letters".to_string(),
+            ],
+        );
+    }
+    if score > 2 {
+        return (None, Vec::new());
+    }
+
+    let longest = sequence
+        .iter()
+        .max_by_key(|m| m.token.len())
+        .expect("sequence is not empty");
+    let (warning, mut extra) = match_feedback(longest, sequence.len() == 1);
+
+    let mut suggestions = vec!["Add another word or two. Uncommon words are better.".to_string()];
+    suggestions.append(&mut extra);
+    (warning, suggestions)
+}
+
+fn match_feedback(m: &Match, sole_match: bool) -> (Option<String>, Vec<String>) {
+    let text = |s: &str| Some(s.to_string());
+
+    match &m.pattern {
+        Pattern::Dictionary {
+            kind,
+            rank,
+            reversed,
+            l33t,
+        } => {
+            let warning = match kind {
+                DictKind::Passwords if sole_match && l33t.is_empty() && !reversed => match rank {
+                    0..=10 => text("This is a top-10 common password"),
+                    11..=100 => text("This is a top-100 common password"),
+                    _ => text("This is a very common password"),
+                },
+                DictKind::Passwords => text("This is similar to a commonly used password"),
+                DictKind::English if sole_match => text("A word by itself is easy to guess"),
+                DictKind::Names if sole_match => text("Names and surnames by themselves are easy to guess"),
+                DictKind::Names => text("Common names and surnames are easy to guess"),
+                DictKind::UserInputs => text("Names and account details are easy to guess"),
+                _ => None,
+            };
+
+            let mut suggestions = Vec::new();
+            let upper = m.token.iter().filter(|c| c.is_uppercase()).count();
+            if upper == m.token.len() {
+                suggestions.push("All-uppercase is almost as easy to guess as all-lowercase".to_string());
+            } else if m.token.first().is_some_and(|c| c.is_uppercase()) {
+                suggestions.push("Capitalization doesn't help very much".to_string());
+            }
+            if *reversed && m.token.len() >= 4 {
+                suggestions.push("Reversed words aren't much harder to guess".to_string());
+            }
+            if !l33t.is_empty() {
+                suggestions.push("Predictable substitutions like '@' instead of 'a' don't help very much".to_string());
+            }
+            (warning, suggestions)
+        }
+        Pattern::Spatial { turns, .. } => {
+            let warning = if *turns == 1 {
+                "Straight rows of keys are easy to guess"
+            } else {
+                "Short keyboard patterns are easy to guess"
+            };
+            (
+                text(warning),
+                vec!["Use a longer keyboard pattern with more turns".to_string()],
+            )
+        }
+        Pattern::Repeat { base_len, .. } => {
+            let warning = if *base_len == 1 {
+                "Repeats like \"aaa\" are easy to guess"
+            } else {
+                "Repeats like \"abcabcabc\" are only slightly harder to guess than \"abc\""
+            };
+            (text(warning), vec!["Avoid repeated words and characters".to_string()])
+        }
+        Pattern::Sequence { .. } => (
+            text("Sequences like abc or 6543 are easy to guess"),
+            vec!["Avoid sequences".to_string()],
+        ),
+        Pattern::Year { .. } => (
+            text("Recent years are easy to guess"),
+            vec![
+                "Avoid recent years".to_string(),
+                "Avoid years that are associated with you".to_string(),
+            ],
+        ),
+        Pattern::Date { .. } => (
+            text("Dates are often easy to guess"),
+            vec!["Avoid dates and years that are associated with you".to_string()],
+        ),
+        Pattern::Bruteforce => (None, Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(password: &str) -> Vec<Pattern> {
+        let chars: Vec<char> = password.chars().collect();
+        most_guessable(&chars, &HashMap::new())
+            .1
+            .into_iter()
+            .map(|m| m.pattern)
+            .collect()
+    }
+
+    #[test]
+    fn common_passwords_score_zero() {
+        for password in ["password", "123456", "Password", "qwerty"] {
+            let estimate = estimate(password, &[]);
+            assert_eq!(estimate.score, 0, "{}", password);
+        }
+        let warning = estimate("password", &[]).warning;
+        assert_eq!(warning.as_deref(), Some("This is a top-10 common password"));
+    }
+
+    #[test]
+    fn dictionaries_are_ranked_by_frequency() {
+        let (kind, english) = &dictionaries()[1];
+        assert_eq!(*kind, DictKind::English);
+        assert!(english.len() >= 30_000);
+        assert!(english["the"] < english["water"]);
+        assert!(english["river"] < english["water"]);
+        assert!(dictionaries()[0].1.len() >= 30_000);
+    }
+
+    #[test]
+    fn common_words_joined_together_score_low() {
+        for password in ["elephantgiraffe", "keyboardmonitor", "tablechair", "sunshinewater"] {
+            let estimate = estimate(password, &[]);
+            assert!(estimate.score <= 2, "{} scored {}", password, estimate.score);
+        }
+        // A third word, capitals or a separator add a little, but not the most
+        for password in ["tablechairlamp", "Elephant-Giraffe"] {
+            assert!(estimate(password, &[]).score < MAX_SCORE, "{}", password);
+        }
+    }
+
+    #[test]
+    fn names_are_found() {
+        let estimate = estimate("jennifer", &[]);
+        assert!(estimate.score <= 1);
+        assert!(patterns("smithjohnson")
+            .iter()
+            .all(|p| matches!(p, Pattern::Dictionary { kind: DictKind::Names, .. })));
+    }
+
+    #[test]
+    fn keyboard_walks_are_spatial() {
+        for password in ["ertyuiop", "xcvbnm,.", "hjkl;'"] {
+            assert!(
+                patterns(password)
+                    .iter()
+                    .any(|p| matches!(p, Pattern::Spatial { .. })),
+                "{}",
+                password
+            );
+            assert!(estimate(password, &[]).score <= 1, "{}", password);
+        }
+    }
+
+    #[test]
+    fn dates_are_found() {
+        for password in ["11/09/1991", "1991-09-11", "19910911"] {
+            assert!(
+                patterns(password)
+                    .iter()
+                    .any(|p| matches!(p, Pattern::Date { year: 1991, .. })),
+                "{}",
+                password
+            );
+            assert!(estimate(password, &[]).score <= 1, "{}", password);
+        }
+    }
+
+    #[test]
+    fn user_inputs_lower_the_estimate() {
+        let password = "Fairbanks-Quill";
+        let without = estimate(password, &[]);
+        let with = estimate(password, &["fairbanks", "quill"]);
+        assert!(with.guesses < without.guesses);
+        assert!(with.score < without.score);
+    }
+
+    #[test]
+    fn long_random_strings_score_four() {
+        for password in ["x7$Kq!2vRz@9pLm#4WtB", "Tq8#vN2!pRz5@kWm"] {
+            assert_eq!(estimate(password, &[]).score, MAX_SCORE, "{}", password);
+        }
+    }
+
+    #[test]
+    fn verdict_applies_the_policy() {
+        let warn = StrengthPolicy::default();
+        let refuse = StrengthPolicy {
+            enforcement: Enforcement::Refuse,
+            ..warn
+        };
+        let weak = estimate("password", &[]);
+        let strong = estimate("x7$Kq!2vRz@9pLm#4WtB", &[]);
+
+        assert_eq!(warn.verdict(&strong, 0), Verdict::Accept);
+        assert_eq!(warn.verdict(&weak, 0), Verdict::Warn(Shortfall::BelowMinimum(DEFAULT_MIN_SCORE)));
+        assert_eq!(refuse.verdict(&weak, 0), Verdict::Refuse(Shortfall::BelowMinimum(DEFAULT_MIN_SCORE)));
+        // A breached password is refused however strong it looks
+        assert_eq!(refuse.verdict(&strong, 3), Verdict::Refuse(Shortfall::Breached(3)));
+        assert_eq!(refuse.verdict(&weak, 3), Verdict::Refuse(Shortfall::Breached(3)));
+    }
+
+    #[test]
+    fn display_time_picks_a_unit() {
+        assert_eq!(display_time(0.5), "less than a second");
+        assert_eq!(display_time(1.0), "1 second");
+        assert_eq!(display_time(42.0), "42 seconds");
+        assert_eq!(display_time(90.0), "2 minutes");
+        assert_eq!(display_time(3600.0), "1 hour");
+        assert_eq!(display_time(86400.0 * 3.0), "3 days");
+        assert_eq!(display_time(86400.0 * 31.0 * 12.0 * 5.0), "5 years");
+        assert_eq!(display_time(1e12), "centuries");
+    }
+}
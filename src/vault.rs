@@ -0,0 +1,374 @@
+use crate::encryption::{self, EncryptedPassword, KdfParams, KEY_LEN};
+use password_client::{ClientError, PasswordApiClient};
+use password_common::{Credentials, RegisterRequest, UpdateEntry, VaultHeader, VaultParams};
+use std::io;
+use std::path::Path;
+use zeroize::Zeroize;
+
+use crate::hashPassword::{hash_password, needs_rehash, HashParams};
+use crate::secret::{read_hidden, SecretString};
+use crate::session;
+
+// Known plaintext encrypted under the vault key, used to reject a wrong
+// master password when a saved session is reused
+const KEY_CHECK_PLAINTEXT: &str = "aps-password-manager-key-check";
+const MAX_UNLOCK_ATTEMPTS: usize = 3;
+
+fn kdf_params(params: &VaultParams) -> KdfParams {
+    KdfParams {
+        memory_kib: params.kdf_memory_kib,
+        iterations: params.kdf_iterations,
+        parallelism: params.kdf_parallelism,
+    }
+}
+
+// An unlocked vault: the owner it belongs to and the key derived from their
+// master password. The session token lives in the client that unlocked it.
+pub struct Vault {
+    pub owner: String,
+    key: [u8; KEY_LEN],
+}
+
+impl Vault {
+    pub fn encrypt(&self, password: &SecretString) -> EncryptedPassword {
+        encryption::encrypt_password(&self.key, password.expose())
+    }
+
+    pub fn decrypt(&self, ciphertext: &str, nonce: &str) -> Option<SecretString> {
+        encryption::decrypt_password(&self.key, ciphertext, nonce)
+    }
+}
+
+// Replaces the entry's hash and encrypted copy with ones made from this
+// password. The server only takes a new hash together with a new ciphertext.
+pub async fn store_password(
+    client: &PasswordApiClient,
+    vault: &Vault,
+    entry_id: u64,
+    password: &SecretString,
+    params: &HashParams,
+) -> Result<(), String> {
+    let hash = hash_password(password, params).await.map_err(|e| e.to_string())?;
+    let encrypted = vault.encrypt(password);
+    let update = UpdateEntry {
+        password: Some(hash),
+        ciphertext: Some(encrypted.ciphertext),
+        nonce: Some(encrypted.nonce),
+        ..UpdateEntry::default()
+    };
+    client.update_entry(entry_id, &update).await.map_err(|e| e.to_string())
+}
+
+// After the server has confirmed the entry's password, replaces a hash made
+// with other parameters than the current ones, judged by the parameters the
+// server reported. Returns whether the entry was upgraded.
+pub async fn upgrade_hash(
+    client: &PasswordApiClient,
+    vault: &Vault,
+    entry_id: u64,
+    hash_parameters: Option<&str>,
+    password: &SecretString,
+    params: &HashParams,
+) -> Result<bool, String> {
+    match hash_parameters {
+        Some(stored) if needs_rehash(stored, params) => {}
+        _ => return Ok(false),
+    }
+
+    store_password(client, vault, entry_id, password, params).await?;
+    Ok(true)
+}
+
+impl Drop for Vault {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+// Fetches the owner's vault parameters and asks for the master password until
+// the server accepts the login derived from it or the attempts run out. The
+// server answers for owners without a vault too, so after a first failed
+// attempt the user is offered to create one.
+pub async fn unlock(
+    client: &mut PasswordApiClient,
+    owner: &str,
+    token_file: Option<&Path>,
+    hashing: &HashParams,
+) -> Option<Vault> {
+    let params = fetch_params(client, owner).await?;
+
+    for attempt in 0..MAX_UNLOCK_ATTEMPTS {
+        println!("Enter your master password: ");
+        let master_password = match read_hidden() {
+            Ok(s) => s,
+            Err(e) => {
+                println!("Failed to read master password: {}", e);
+                return None;
+            }
+        };
+
+        if let Some(key) = try_master_password(client, &params, &master_password, token_file).await? {
+            println!("Vault unlocked.");
+            let vault = Vault {
+                owner: owner.to_string(),
+                key,
+            };
+            reencrypt_legacy_entries(client, &vault, &master_password, hashing).await;
+            return Some(vault);
+        }
+
+        if attempt > 0 {
+            println!("Incorrect master password.");
+            continue;
+        }
+        println!("Incorrect master password, or {} has no vault yet.", owner);
+        if confirm(&format!("Create a new vault for {}? (y/N)", owner)) {
+            let key = create_vault(client, owner).await?;
+            if !open_session(client, owner, &key, token_file).await? {
+                println!("Failed to log in to the new vault.");
+                return None;
+            }
+            println!("Vault unlocked.");
+            return Some(Vault {
+                owner: owner.to_string(),
+                key,
+            });
+        }
+    }
+
+    println!("Too many failed attempts.");
+    None
+}
+
+// Non-interactive variant of unlock for scripted use. An owner without a
+// vault looks the same as a wrong master password here. Its messages, like
+// those of the helpers it shares with unlock, go to stderr so that stdout
+// carries only the command's output.
+pub async fn unlock_with_password(
+    client: &mut PasswordApiClient,
+    owner: &str,
+    master_password: &SecretString,
+    token_file: Option<&Path>,
+    hashing: &HashParams,
+) -> Option<Vault> {
+    let params = fetch_params(client, owner).await?;
+
+    match try_master_password(client, &params, master_password, token_file).await? {
+        Some(key) => {
+            let vault = Vault {
+                owner: owner.to_string(),
+                key,
+            };
+            reencrypt_legacy_entries(client, &vault, master_password, hashing).await;
+            Some(vault)
+        }
+        None => {
+            eprintln!("Incorrect master password, or no vault exists for {}.", owner);
+            None
+        }
+    }
+}
+
+// Records saved before vaults existed were encrypted with a key salted with
+// the owner name. Any entry the vault key cannot open is tried with that key
+// and, if it opens, stored again under the vault key; the server only takes
+// a new ciphertext together with a new hash, so it is rehashed as well.
+// Entries that open with neither key are left alone.
+async fn reencrypt_legacy_entries(
+    client: &PasswordApiClient,
+    vault: &Vault,
+    master_password: &SecretString,
+    hashing: &HashParams,
+) {
+    let entries = match client.list_entries(&vault.owner).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Could not check for entries from older versions: {}", e);
+            return;
+        }
+    };
+    let unreadable: Vec<_> = entries
+        .iter()
+        .filter_map(|e| Some((e.id, e.account_ciphertext.as_deref()?, e.account_nonce.as_deref()?)))
+        .filter(|(_, ciphertext, nonce)| vault.decrypt(ciphertext, nonce).is_none())
+        .collect();
+    if unreadable.is_empty() {
+        return;
+    }
+
+    let mut legacy_key = encryption::derive_legacy_key(master_password, &vault.owner).await;
+    let mut reencrypted = 0;
+    for (id, ciphertext, nonce) in unreadable {
+        let Some(password) = encryption::decrypt_password(&legacy_key, ciphertext, nonce) else {
+            continue;
+        };
+        match store_password(client, vault, id, &password, hashing).await {
+            Ok(()) => reencrypted += 1,
+            Err(e) => eprintln!("Could not re-encrypt entry {}: {}", id, e),
+        }
+    }
+    legacy_key.zeroize();
+
+    if reencrypted > 0 {
+        eprintln!("Re-encrypted {} entries saved by an older version under the vault key.", reencrypted);
+    }
+}
+
+// Some(None) means the master password was refused
+async fn try_master_password(
+    client: &mut PasswordApiClient,
+    params: &VaultParams,
+    master_password: &SecretString,
+    token_file: Option<&Path>,
+) -> Option<Option<[u8; KEY_LEN]>> {
+    let key = match encryption::derive_key(master_password, &params.kdf_salt, &kdf_params(params)).await {
+        Some(k) => k,
+        None => {
+            eprintln!("Vault header has invalid key derivation parameters.");
+            return None;
+        }
+    };
+
+    if open_session(client, &params.owner, &key, token_file).await? {
+        Some(Some(key))
+    } else {
+        Some(None)
+    }
+}
+
+async fn fetch_params(client: &PasswordApiClient, owner: &str) -> Option<VaultParams> {
+    match client.get_vault(owner).await {
+        Ok(params) => Some(params),
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+fn confirm(question: &str) -> bool {
+    println!("{}", question);
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return false;
+    }
+    input.trim().eq_ignore_ascii_case("y")
+}
+
+async fn create_vault(client: &PasswordApiClient, owner: &str) -> Option<[u8; KEY_LEN]> {
+    println!("Choose a master password: ");
+    let master_password = read_hidden().ok()?;
+    println!("Confirm the master password: ");
+    let confirmation = read_hidden().ok()?;
+
+    if master_password.is_empty() {
+        println!("The master password cannot be empty.");
+        return None;
+    }
+    if master_password != confirmation {
+        println!("Passwords do not match.");
+        return None;
+    }
+
+    let kdf = kdf_params_from_env();
+    let salt = encryption::generate_salt();
+    let key = match encryption::derive_key(&master_password, &salt, &kdf).await {
+        Some(k) => k,
+        None => {
+            println!("Invalid key derivation parameters: {:?}", kdf);
+            return None;
+        }
+    };
+    let check = encryption::encrypt_password(&key, KEY_CHECK_PLAINTEXT);
+
+    let header = VaultHeader {
+        owner: owner.to_string(),
+        kdf_salt: salt,
+        kdf_memory_kib: kdf.memory_kib,
+        kdf_iterations: kdf.iterations,
+        kdf_parallelism: kdf.parallelism,
+        key_check_ciphertext: check.ciphertext,
+        key_check_nonce: check.nonce,
+    };
+
+    let login_secret = encryption::derive_login_secret(&key);
+    let request = RegisterRequest {
+        owner: owner.to_string(),
+        password: login_secret.expose(),
+        vault: header,
+    };
+
+    match client.register(&request).await {
+        Ok(()) => println!("Vault created."),
+        Err(e) => {
+            println!("{}", e);
+            return None;
+        }
+    }
+
+    Some(key)
+}
+
+// Logs in with the secret derived from the key, unless token_file holds a
+// session that is still valid. Some(false) means the key is wrong. A saved
+// session proves nothing about the key, so it is only reused once the
+// vault's key check decrypts with it.
+async fn open_session(client: &mut PasswordApiClient, owner: &str, key: &[u8; KEY_LEN], token_file: Option<&Path>) -> Option<bool> {
+    if let Some(token) = token_file.and_then(|path| session::load(path, client.base_url(), owner)) {
+        client.set_token(Some(token));
+        match key_matches(client, owner, key).await {
+            Ok(true) => return Some(true),
+            Ok(false) => {
+                client.set_token(None);
+                return Some(false);
+            }
+            // Revoked or expired early; log in again
+            Err(e) if e.is_unauthorized() => client.set_token(None),
+            Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            }
+        }
+    }
+
+    let login_secret = encryption::derive_login_secret(key);
+    let credentials = Credentials {
+        owner: owner.to_string(),
+        password: login_secret.expose(),
+    };
+
+    match client.login(&credentials).await {
+        Ok(login) => {
+            if let Some(path) = token_file {
+                session::save(path, client.base_url(), owner, &login);
+            }
+            Some(true)
+        }
+        Err(e) if e.is_unauthorized() => Some(false),
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+async fn key_matches(client: &PasswordApiClient, owner: &str, key: &[u8; KEY_LEN]) -> Result<bool, ClientError> {
+    let check = client.get_key_check(owner).await?;
+    let plaintext = encryption::decrypt_password(key, &check.ciphertext, &check.nonce);
+    Ok(plaintext.as_ref().map(SecretString::expose) == Some(KEY_CHECK_PLAINTEXT))
+}
+
+// New vaults use the default Argon2id costs unless overridden with
+// APS_KDF_MEMORY_KIB, APS_KDF_ITERATIONS or APS_KDF_PARALLELISM.
+fn kdf_params_from_env() -> KdfParams {
+    fn env_u32(name: &str) -> Option<u32> {
+        std::env::var(name).ok()?.trim().parse().ok()
+    }
+
+    let defaults = KdfParams::default();
+    KdfParams {
+        memory_kib: env_u32("APS_KDF_MEMORY_KIB").unwrap_or(defaults.memory_kib),
+        iterations: env_u32("APS_KDF_ITERATIONS").unwrap_or(defaults.iterations),
+        parallelism: env_u32("APS_KDF_PARALLELISM").unwrap_or(defaults.parallelism),
+    }
+}